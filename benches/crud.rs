@@ -0,0 +1,128 @@
+use std::{
+    collections::HashMap
+    , time::{Duration as StdDuration, Instant}
+};
+use criterion::{criterion_group, criterion_main, Criterion};
+use serde_json::json;
+use surrealdb::{
+    Surreal
+    , engine::local::{Db, Mem}
+};
+use tokio::runtime::Runtime;
+use tower_sessions::{
+    ExpiredDeletion
+    , SessionStore
+    , cookie::time::{OffsetDateTime, Duration}
+    , session::{Id, Record}
+};
+use tower_sessions_surrealdb_store::{SchemaApplyMode, SurrealdbStore, SurrealdbStoreConfig};
+
+/// Builds a store on top of an in-memory SurrealDB instance so that the
+/// numbers are not polluted by network noise.
+fn in_memory_store(runtime: &Runtime) -> SurrealdbStore<Db> {
+    runtime.block_on(async {
+        let my_surreal: Surreal<Db> = Surreal::new::<Mem>(()).await
+            .expect("Could not start in-memory SurrealDB");
+        let store = SurrealdbStore::from_config(
+            SurrealdbStoreConfig {
+                namespace: Some("bench_namespace".into())
+                , database: Some("bench_database".into())
+                , ..SurrealdbStoreConfig::default()
+            }
+            , my_surreal
+        );
+        store.ensure_namespace_and_database().await
+            .expect("Could not define namespace and database");
+        store.create_data_model(SchemaApplyMode::IfNotExists).await
+            .expect("Could not create data model");
+        store
+    })
+}
+
+fn new_record(expiry_date: OffsetDateTime) -> Record {
+    let mut data = HashMap::new();
+    data.insert("bench_key".to_string(), json!("bench_value"));
+    Record {
+        id: Id(0)
+        , data: data
+        , expiry_date: expiry_date
+    }
+}
+
+fn live_record() -> Record {
+    new_record(OffsetDateTime::now_utc().saturating_add(Duration::weeks(1)))
+}
+
+fn expired_record() -> Record {
+    new_record(OffsetDateTime::now_utc().saturating_sub(Duration::minutes(5)))
+}
+
+fn crud(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("Could not build tokio runtime");
+    let store = in_memory_store(&runtime);
+    let existing = runtime.block_on(async {
+        let mut record = live_record();
+        store.create(&mut record).await.expect("Could not create bench record");
+        record
+    });
+
+    c.bench_function("create", |b| {
+        b.to_async(&runtime).iter(|| async {
+            let mut record = live_record();
+            store.create(&mut record).await.expect("Could not create record");
+        })
+    });
+    c.bench_function("load", |b| {
+        b.to_async(&runtime).iter(|| async {
+            store.load(&existing.id).await.expect("Could not load record");
+        })
+    });
+    c.bench_function("save", |b| {
+        b.to_async(&runtime).iter(|| async {
+            store.save(&existing).await.expect("Could not save record");
+        })
+    });
+    c.bench_function("delete", |b| {
+        b.to_async(&runtime).iter_custom(|iters| {
+            let store = &store;
+            async move {
+                let mut ids = Vec::with_capacity(iters as usize);
+                for _ in 0..iters {
+                    let mut record = live_record();
+                    store.create(&mut record).await.expect("Could not create record");
+                    ids.push(record.id);
+                }
+                let start = Instant::now();
+                for id in &ids {
+                    store.delete(id).await.expect("Could not delete record");
+                }
+                start.elapsed()
+            }
+        })
+    });
+
+    let mut group = c.benchmark_group("delete_expired");
+    group.sample_size(10);
+    group.bench_function("1000_expired_sessions", |b| {
+        b.to_async(&runtime).iter_custom(|iters| {
+            let store = &store;
+            async move {
+                let mut total = StdDuration::ZERO;
+                for _ in 0..iters {
+                    for _ in 0..1000 {
+                        let mut record = expired_record();
+                        store.create(&mut record).await.expect("Could not create record");
+                    }
+                    let start = Instant::now();
+                    store.delete_expired().await.expect("Could not delete expired records");
+                    total += start.elapsed();
+                }
+                total
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, crud);
+criterion_main!(benches);
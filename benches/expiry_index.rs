@@ -0,0 +1,102 @@
+use std::time::{Duration as StdDuration, Instant};
+use criterion::{criterion_group, criterion_main, Criterion};
+use surrealdb::{
+    Surreal
+    , engine::local::{Db, Mem}
+};
+use tokio::runtime::Runtime;
+use tower_sessions::ExpiredDeletion;
+use tower_sessions_surrealdb_store::{SchemaApplyMode, SurrealdbStore, SurrealdbStoreConfig};
+
+const LIVE_SESSIONS: u32 = 100_000;
+const EXPIRED_SESSIONS: u32 = 1_000;
+
+/// Builds a store on an in-memory instance with `LIVE_SESSIONS` live
+/// sessions in `sessions_table`. When `with_index` is false the expiry
+/// index created by `create_data_model` is removed again.
+fn populated_store(
+    runtime: &Runtime
+    , sessions_table: &str
+    , with_index: bool
+) -> (Surreal<Db>, SurrealdbStore<Db>) {
+    runtime.block_on(async {
+        let my_surreal: Surreal<Db> = Surreal::new::<Mem>(()).await
+            .expect("Could not start in-memory SurrealDB");
+        let store = SurrealdbStore::from_config(
+            SurrealdbStoreConfig {
+                sessions_table: sessions_table.into()
+                , namespace: Some("bench_namespace".into())
+                , database: Some("bench_database".into())
+                , ..SurrealdbStoreConfig::default()
+            }
+            , my_surreal.clone()
+        );
+        store.ensure_namespace_and_database().await
+            .expect("Could not define namespace and database");
+        store.create_data_model(SchemaApplyMode::IfNotExists).await
+            .expect("Could not create data model");
+        if !with_index {
+            my_surreal.query(format!(
+                    "REMOVE INDEX {sessions_table}_expiry_date_index ON TABLE {sessions_table};"
+                ))
+                .await
+                .and_then(|response| response.check())
+                .expect("Could not remove expiry index");
+        }
+        my_surreal.query(r#"
+                FOR $i IN 1..=$count {
+                    CREATE type::thing($table, $i) SET
+                        expiry_date = time::now() + 1w
+                        , record = <bytes> "bench";
+                };
+            "#).bind(("table", sessions_table.to_string()))
+            .bind(("count", LIVE_SESSIONS))
+            .await
+            .and_then(|response| response.check())
+            .expect("Could not populate live sessions");
+        (my_surreal, store)
+    })
+}
+
+/// Each iteration adds `EXPIRED_SESSIONS` expired sessions next to the
+/// live ones and times only the `delete_expired` call.
+fn delete_expired(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("Could not build tokio runtime");
+    let mut group = c.benchmark_group("delete_expired_100000_sessions");
+    group.sample_size(10);
+    for (name, with_index) in [("with_index", true), ("without_index", false)] {
+        let sessions_table = format!("{name}_sessions");
+        let (my_surreal, store) = populated_store(&runtime, &sessions_table, with_index);
+        group.bench_function(name, |b| {
+            b.to_async(&runtime).iter_custom(|iters| {
+                let my_surreal = &my_surreal;
+                let store = &store;
+                let sessions_table = &sessions_table;
+                async move {
+                    let mut total = StdDuration::ZERO;
+                    for _ in 0..iters {
+                        my_surreal.query(r#"
+                                FOR $i IN 1..=$count {
+                                    CREATE type::thing($table, rand::int(200000, 9000000000)) SET
+                                        expiry_date = time::now() - 1h
+                                        , record = <bytes> "bench";
+                                };
+                            "#).bind(("table", sessions_table.clone()))
+                            .bind(("count", EXPIRED_SESSIONS))
+                            .await
+                            .and_then(|response| response.check())
+                            .expect("Could not populate expired sessions");
+                        let start = Instant::now();
+                        store.delete_expired().await.expect("Could not delete expired records");
+                        total += start.elapsed();
+                    }
+                    total
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, delete_expired);
+criterion_main!(benches);
@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+use criterion::{criterion_group, criterion_main, Criterion};
+use serde_json::json;
+use surrealdb::engine::any::Any;
+use tokio::runtime::Runtime;
+use tower_sessions::{
+    SessionStore
+    , cookie::time::{OffsetDateTime, Duration}
+    , session::{Id, Record}
+};
+use tower_sessions_surrealdb_store::{SchemaApplyMode, SurrealdbStore};
+
+/// Compares a full `load` against `get_session_metadata_only` for a
+/// session carrying a 10 KB payload. Needs a SurrealDB instance on
+/// localhost:8000 and the DB_PASSWORD env var, same as the tests.
+fn load_vs_metadata(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("Could not build tokio runtime");
+    let (store, id) = runtime.block_on(async {
+        let store: SurrealdbStore<Any> = SurrealdbStore::new_from_nothing(
+            "ws".into()
+            , "localhost:8000".into()
+            , "root".into()
+            , "namespace".into()
+            , "database".into()
+            , "bench_sessions".into()
+            , "bench_sessions_latest_id".into()
+            , false
+        ).await.expect("Connecting to SurrealDB with the specified config failed");
+        store.create_data_model(SchemaApplyMode::IfNotExists).await
+            .expect("Could not create data model");
+        let mut data = HashMap::new();
+        data.insert("payload".to_string(), json!("x".repeat(10 * 1024)));
+        let mut record = Record {
+            id: Id(0)
+            , data: data
+            , expiry_date: OffsetDateTime::now_utc().saturating_add(Duration::weeks(1))
+        };
+        store.create(&mut record).await.expect("Could not create bench record");
+        (store, record.id)
+    });
+
+    let mut group = c.benchmark_group("10kb_session");
+    group.bench_function("load", |b| {
+        b.to_async(&runtime).iter(|| store.load(&id))
+    });
+    group.bench_function("get_session_metadata_only", |b| {
+        b.to_async(&runtime).iter(|| store.get_session_metadata_only(&id))
+    });
+    group.finish();
+
+    runtime.block_on(store.delete(&id)).expect("Could not delete bench record");
+}
+
+criterion_group!(benches, load_vs_metadata);
+criterion_main!(benches);
@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use base64::{prelude::BASE64_STANDARD_NO_PAD, Engine};
+use criterion::{criterion_group, criterion_main, Criterion};
+use serde_bytes::ByteBuf;
+use serde_json::json;
+use surrealdb::{
+    Surreal
+    , engine::local::{Db, Mem}
+};
+use tokio::runtime::Runtime;
+use tower_sessions::{
+    SessionStore
+    , cookie::time::{OffsetDateTime, Duration}
+    , session::{Id, Record}
+};
+use tower_sessions_surrealdb_store::{SchemaApplyMode, SurrealdbStore, SurrealdbStoreConfig};
+
+const SESSION_SIZE: usize = 64 * 1024;
+
+fn large_record() -> Record {
+    let mut data = HashMap::new();
+    data.insert("payload".to_string(), json!("x".repeat(SESSION_SIZE)));
+    Record {
+        id: Id(0)
+        , data: data
+        , expiry_date: OffsetDateTime::now_utc().saturating_add(Duration::weeks(1))
+    }
+}
+
+/// Compares sending a 64 KiB session as bound bytes, which `create` does,
+/// against the base64 string plus `encoding::base64::decode` it used to
+/// send.
+fn payload(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("Could not build tokio runtime");
+    let my_surreal: Surreal<Db> = runtime.block_on(async {
+        let my_surreal: Surreal<Db> = Surreal::new::<Mem>(()).await
+            .expect("Could not start in-memory SurrealDB");
+        my_surreal.use_ns("bench_namespace").use_db("bench_database").await
+            .expect("Could not select namespace and database");
+        my_surreal
+    });
+    let store = SurrealdbStore::from_config(SurrealdbStoreConfig::default(), my_surreal.clone());
+    runtime.block_on(store.create_data_model(SchemaApplyMode::IfNotExists))
+        .expect("Could not create data model");
+
+    let msgpack = rmp_serde::to_vec(&large_record()).expect("Could not encode record");
+    let base64 = BASE64_STANDARD_NO_PAD.encode(&msgpack);
+    println!(
+        "64 KiB session: {} bytes bound directly, {} bytes as base64 ({:+.1}%)"
+        , msgpack.len()
+        , base64.len()
+        , (base64.len() as f64 / msgpack.len() as f64 - 1.0) * 100.0
+    );
+
+    let mut group = c.benchmark_group("payload_64_kib");
+    group.bench_function("bound_bytes", |b| {
+        b.to_async(&runtime).iter(|| async {
+            my_surreal.query("CREATE payload_bench SET record = $record")
+                .bind(("record", ByteBuf::from(msgpack.clone())))
+                .await
+                .expect("Could not create payload row");
+        })
+    });
+    group.bench_function("base64_string", |b| {
+        b.to_async(&runtime).iter(|| async {
+            my_surreal.query("CREATE payload_bench SET record = encoding::base64::decode($record)")
+                .bind(("record", base64.clone()))
+                .await
+                .expect("Could not create payload row");
+        })
+    });
+    group.bench_function("store_create", |b| {
+        b.to_async(&runtime).iter(|| async {
+            let mut record = large_record();
+            store.create(&mut record).await.expect("Could not create session");
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, payload);
+criterion_main!(benches);
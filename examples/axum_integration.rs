@@ -0,0 +1,75 @@
+//! Counts how often a client visited, keeping the count in its session.
+//!
+//! Run with `cargo run --example axum_integration` and open
+//! http://127.0.0.1:3000 a few times. Sessions live in an in-memory
+//! SurrealDB; to use a SurrealDB server build the store with
+//! `SurrealdbStore::new_from_nothing` instead.
+use axum::{http::StatusCode, routing::get, Router};
+use surrealdb::engine::any;
+use tower_sessions::{cookie::time::Duration, Expiry, Session, SessionManagerLayer};
+use tower_sessions_surrealdb_store::{SchemaApplyMode, SurrealdbStore, SurrealdbStoreConfig};
+
+const COUNTER_KEY: &str = "counter";
+
+async fn counter(session: Session) -> Result<String, StatusCode> {
+    let count = session.get::<u64>(COUNTER_KEY).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .unwrap_or_default() + 1;
+    session.insert(COUNTER_KEY, count).await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(count.to_string())
+}
+
+async fn app(endpoint: &str) -> anyhow::Result<Router> {
+    let client = any::connect(endpoint).await?;
+    client.use_ns("example").use_db("example").await?;
+    let store = SurrealdbStore::from_config(SurrealdbStoreConfig::default(), client);
+    store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    let session_layer = SessionManagerLayer::new(store)
+        .with_secure(false)
+        .with_expiry(Expiry::OnInactivity(Duration::hours(1)));
+    Ok(Router::new()
+        .route("/", get(counter))
+        .layer(session_layer))
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:3000").await?;
+    axum::serve(listener, app("mem://").await?).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use axum::{
+        body::{to_bytes, Body}
+        , http::{header, Request}
+    };
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn counter_increments_per_session() -> anyhow::Result<()> {
+        let app = super::app("mem://").await?;
+
+        let response = app.clone()
+            .oneshot(Request::get("/").body(Body::empty())?)
+            .await?;
+        let cookie = response.headers()
+            .get(header::SET_COOKIE)
+            .ok_or(anyhow!("No session cookie was set"))?
+            .to_str()?
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        assert_eq!(&to_bytes(response.into_body(), usize::MAX).await?[..], b"1");
+
+        let response = app
+            .oneshot(Request::get("/").header(header::COOKIE, cookie).body(Body::empty())?)
+            .await?;
+        assert_eq!(&to_bytes(response.into_body(), usize::MAX).await?[..], b"2");
+        Ok(())
+    }
+}
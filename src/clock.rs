@@ -0,0 +1,64 @@
+use std::sync::{Arc, Mutex, PoisonError};
+use tower_sessions::cookie::time::{Duration, OffsetDateTime};
+
+/// Source of the current time for every expiry decision the store makes
+/// on the client side, such as rejecting already expired sessions on
+/// create. With `with_bind_now` the same time is also sent to SurrealDB in
+/// place of `time::now()`.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> OffsetDateTime;
+}
+
+/// Reads the system clock. This is what the store uses when no clock is
+/// configured.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+}
+
+/// Clock which only moves when told to, so that expiry boundaries can be
+/// tested without sleeping. Clones share the same time.
+/// ```
+/// # use surrealdb::{Surreal, engine::local::Mem};
+/// # use tower_sessions::cookie::time::{Duration, OffsetDateTime};
+/// # use tower_sessions_surrealdb_store::{ManualClock, SurrealdbStore};
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// # let my_surreal = Surreal::new::<Mem>(()).await?;
+/// # my_surreal.use_ns("namespace").use_db("database").await?;
+/// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+/// let clock = ManualClock::new(OffsetDateTime::UNIX_EPOCH);
+/// let my_surreal_store = my_surreal_store
+///     .with_clock(clock.clone())
+///     .with_bind_now(true);
+/// clock.advance(Duration::hours(1));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ManualClock(Arc<Mutex<OffsetDateTime>>);
+
+impl ManualClock {
+    pub fn new(now: OffsetDateTime) -> Self {
+        Self(Arc::new(Mutex::new(now)))
+    }
+
+    pub fn set(&self, now: OffsetDateTime) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = now;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        *now = now.saturating_add(by);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> OffsetDateTime {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -0,0 +1,240 @@
+use std::{error::Error, fmt};
+use tower_sessions::{
+    cookie::time::OffsetDateTime
+    , session::Id
+    , session_store
+};
+
+/// Errors raised by the SurrealDB session store. They keep the original
+/// error around and are converted into `session_store::Error` where the
+/// `SessionStore` trait requires it.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SurrealdbError {
+    /// A query sent to SurrealDB failed
+    QueryFailed(surrealdb::Error),
+    /// The session that was operated on does not exist, usually because it
+    /// expired or was deleted by another request after it was loaded
+    RecordNotFound(Id),
+    /// `delete` was called for a session that does not exist while
+    /// `MissingDeleteBehavior::Error` is configured
+    MissingSession(Id),
+    /// A session could not be serialized for storage
+    SerializationFailed(Box<dyn Error + Send + Sync>),
+    /// A stored session could not be deserialized
+    DeserializationFailed(Box<dyn Error + Send + Sync>),
+    /// The stored record of a session could not be decoded
+    SessionDecodeFailed {
+        id: Id,
+        /// Length of the stored record in bytes
+        length: usize,
+        source: Box<SurrealdbError>
+    },
+    /// The stored record of a session doesn't match its checksum
+    ChecksumMismatch {
+        /// Format version of the stored record
+        version: u8,
+        expected: u32,
+        actual: u32
+    },
+    /// The stored record of a session has a format version this release
+    /// can't read, e.g. because it was written by a newer one
+    UnknownRecordVersion(u8),
+    /// A record key could not be converted into a session ID
+    IdConversionFailed(String),
+    /// Another transaction wrote to the same records
+    TransactionConflict,
+    /// A versioned `save` found that the session was saved by someone else
+    /// since it was loaded. Through the `SessionStore` trait it surfaces as
+    /// `session_store::Error::Backend`, see `is_version_conflict`
+    VersionConflict {
+        id: Id,
+        /// Version the session had when it was loaded
+        expected: u64,
+        /// Version the session has now
+        found: u64
+    },
+    /// A table or other identifier can not be used in a query
+    InvalidIdentifier(String),
+    /// A session that is already expired was about to be written
+    SessionAlreadyExpired {
+        id: Id,
+        expiry_date: OffsetDateTime,
+        now: OffsetDateTime
+    },
+    /// An encoded session is larger than
+    /// `SurrealdbStoreConfig::max_payload_bytes`
+    SessionTooLarge {
+        id: Id,
+        /// Length of the encoded session in bytes
+        size: usize,
+        limit: usize
+    },
+    /// The ID counter is negative or about to overflow, so `create` can't
+    /// hand out a valid session ID. `SurrealdbStore::reset_counter` fixes it
+    CounterOutOfRange {
+        counter_table: String,
+        /// The error SurrealDB reported
+        reason: String
+    },
+    /// A record user tried to take a session ID from the counter, which
+    /// record users may not write. Stores signed in through a record access
+    /// need an ID generator
+    IdGeneratorRequired {
+        record_access: String
+    },
+    /// Any other failure
+    Unknown(String)
+}
+
+impl fmt::Display for SurrealdbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SurrealdbError::QueryFailed(e) => write!(f, "SurrealDB query failed: {e}")
+            , SurrealdbError::RecordNotFound(id) => write!(
+                f
+                , "Session {id} was not found. It probably expired or was deleted \
+                by another request after it was loaded"
+            )
+            , SurrealdbError::MissingSession(id) => write!(f, "There is no session with ID {id} to delete")
+            , SurrealdbError::SerializationFailed(e) => write!(f, "Could not serialize session: {e}")
+            , SurrealdbError::DeserializationFailed(e) => write!(f, "Could not deserialize session: {e}")
+            , SurrealdbError::SessionDecodeFailed { id, length, source } => write!(
+                f
+                , "Session {id} could not be decoded from its {length} byte record: {source}"
+            )
+            , SurrealdbError::ChecksumMismatch { version, expected, actual } => write!(
+                f
+                , "Session record of format version {version} is corrupted: checksum is \
+                {actual:#010x} but {expected:#010x} was stored"
+            )
+            , SurrealdbError::UnknownRecordVersion(version) => write!(
+                f
+                , "Session record has unknown format version {version}. It was probably \
+                written by a newer release"
+            )
+            , SurrealdbError::IdConversionFailed(key) => write!(f, "Record key {key} is not a session ID")
+            , SurrealdbError::TransactionConflict => write!(
+                f
+                , "The transaction conflicted with another one writing the same records"
+            )
+            , SurrealdbError::VersionConflict { id, expected, found } => write!(
+                f
+                , "{VERSION_CONFLICT_MARKER}: session {id} was saved concurrently. It was \
+                loaded at version {expected} but is now at version {found}"
+            )
+            , SurrealdbError::InvalidIdentifier(identifier) => write!(
+                f
+                , "Identifier can not be used in a query: {identifier:?}"
+            )
+            , SurrealdbError::SessionAlreadyExpired { id, expiry_date, now } => write!(
+                f
+                , "Refusing to write session {id} because it is already expired. \
+                Expiry date was {expiry_date} and current time is {now}"
+            )
+            , SurrealdbError::SessionTooLarge { id, size, limit } => write!(
+                f
+                , "Session {id} is {size} bytes once encoded, which is more than the \
+                limit of {limit} bytes"
+            )
+            , SurrealdbError::CounterOutOfRange { counter_table, reason } => write!(
+                f
+                , "The session ID counter in {counter_table} can't hand out a valid ID ({reason}). \
+                Run reset_counter to move it past the existing sessions"
+            )
+            , SurrealdbError::IdGeneratorRequired { record_access } => write!(
+                f
+                , "Record users of {record_access} may not write the session ID counter. \
+                Set an ID generator such as UuidV4IdGenerator with with_id_generator"
+            )
+            , SurrealdbError::Unknown(message) => f.write_str(message)
+        }
+    }
+}
+
+impl Error for SurrealdbError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SurrealdbError::QueryFailed(e) => Some(e)
+            , SurrealdbError::SerializationFailed(e) => Some(e.as_ref())
+            , SurrealdbError::DeserializationFailed(e) => Some(e.as_ref())
+            , SurrealdbError::SessionDecodeFailed { source, .. } => Some(source.as_ref())
+            , _ => None
+        }
+    }
+}
+
+impl From<surrealdb::Error> for SurrealdbError {
+    fn from(e: surrealdb::Error) -> Self {
+        SurrealdbError::QueryFailed(e)
+    }
+}
+
+/// Start of the message of a `SurrealdbError::VersionConflict` converted
+/// into a `session_store::Error`.
+pub const VERSION_CONFLICT_MARKER: &str = "Session version conflict";
+
+/// Tells whether an error returned through the `SessionStore` trait is a
+/// `SurrealdbError::VersionConflict`. The trait's error only has the
+/// `Encode`, `Decode` and `Backend` variants and carries a message, so the
+/// conflict is a `Backend` error recognized by the marker it starts with.
+pub fn is_version_conflict(error: &session_store::Error) -> bool {
+    matches!(error, session_store::Error::Backend(message) if message.starts_with(VERSION_CONFLICT_MARKER))
+}
+
+impl From<SurrealdbError> for session_store::Error {
+    fn from(e: SurrealdbError) -> Self {
+        match e {
+            SurrealdbError::SerializationFailed(_)
+            | SurrealdbError::InvalidIdentifier(_)
+            | SurrealdbError::SessionAlreadyExpired { .. }
+            | SurrealdbError::SessionTooLarge { .. } => session_store::Error::Encode(e.to_string())
+            , SurrealdbError::DeserializationFailed(_)
+            | SurrealdbError::SessionDecodeFailed { .. }
+            | SurrealdbError::ChecksumMismatch { .. }
+            | SurrealdbError::UnknownRecordVersion(_)
+            | SurrealdbError::IdConversionFailed(_) => session_store::Error::Decode(e.to_string())
+            , _ => session_store::Error::Backend(e.to_string())
+        }
+    }
+}
+
+/// Operation of `SurrealdbStore::test_connection` that failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionTestStep {
+    Create,
+    Load,
+    Save,
+    /// The load after the save
+    Reload,
+    Delete
+}
+
+impl fmt::Display for ConnectionTestStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConnectionTestStep::Create => "create"
+            , ConnectionTestStep::Load => "load"
+            , ConnectionTestStep::Save => "save"
+            , ConnectionTestStep::Reload => "reload"
+            , ConnectionTestStep::Delete => "delete"
+        })
+    }
+}
+
+/// Returned by `SurrealdbStore::test_connection`, inside an
+/// `anyhow::Error`, when one of its steps fails.
+#[derive(Debug)]
+pub struct TestConnectionError {
+    pub step: ConnectionTestStep,
+    /// What went wrong in that step
+    pub reason: String
+}
+
+impl fmt::Display for TestConnectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Connection test failed at the {} step: {}", self.step, self.reason)
+    }
+}
+
+impl Error for TestConnectionError {}
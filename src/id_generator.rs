@@ -0,0 +1,118 @@
+use std::fmt::Debug;
+use async_trait::async_trait;
+use surrealdb::{Surreal, Connection};
+use tower_sessions::{
+    cookie::time::OffsetDateTime
+    , session::Id
+    , session_store
+};
+use uuid::Uuid;
+use crate::SurrealdbError;
+
+/// Strategy used by `SurrealdbStore::create` to pick the ID of a new
+/// session. The returned value is stored bit for bit as the `i128` of the
+/// session ID, so every `u128` is a valid ID. When the picked ID is
+/// already taken the store asks for another one.
+#[async_trait]
+pub trait IdGenerator: Send + Sync {
+    async fn next_id(&self) -> session_store::Result<u128>;
+}
+
+/// Hands out IDs from a counter record in SurrealDB, one higher than the
+/// last one. This is what the store does when no generator is configured.
+/// ```
+/// # use surrealdb::{Surreal, engine::local::Mem};
+/// # use tower_sessions_surrealdb_store::{CounterIdGenerator, SurrealdbStore};
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// # let my_surreal = Surreal::new::<Mem>(()).await?;
+/// # my_surreal.use_ns("namespace").use_db("database").await?;
+/// # let my_surreal_store = SurrealdbStore::new(my_surreal.clone(), "sessions".into(), "sessions_latest_id".into());
+/// let my_surreal_store = my_surreal_store.with_id_generator(
+///     CounterIdGenerator::new(my_surreal.clone(), "sessions_latest_id".into())
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct CounterIdGenerator<DB>
+where
+    DB: Connection + Debug
+{
+    client: Surreal<DB>,
+    counter_table: String
+}
+
+impl<DB> CounterIdGenerator<DB>
+where
+    DB: Connection + Debug
+{
+    pub fn new(client: Surreal<DB>, counter_table: String) -> Self {
+        Self {
+            client
+            , counter_table
+        }
+    }
+}
+
+#[async_trait]
+impl<DB> IdGenerator for CounterIdGenerator<DB>
+where
+    DB: Connection + Debug
+{
+    async fn next_id(&self) -> session_store::Result<u128> {
+        let num: Option<u64> = self.client
+            .query(r#"UPSERT type::thing($counter_table, "counter") SET num += 1 RETURN AFTER;"#)
+            .bind(("counter_table", self.counter_table.clone()))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .take((0, "num"))
+            .map_err(SurrealdbError::QueryFailed)?;
+        let num = num.ok_or(SurrealdbError::Unknown(
+            "The ID counter was not updated so no ID was returned".into()
+        ))?;
+        Ok(num.into())
+    }
+}
+
+/// Hands out random version 4 UUIDs, so IDs can't be guessed from one
+/// another and no counter record is needed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UuidV4IdGenerator;
+
+#[async_trait]
+impl IdGenerator for UuidV4IdGenerator {
+    async fn next_id(&self) -> session_store::Result<u128> {
+        Ok(Uuid::new_v4().as_u128())
+    }
+}
+
+/// Hands out random IDs, the same way tower-sessions generates them, so
+/// that no counter record is needed. Combined with
+/// `IdAssignment::HonorCallerThenCounter` the random ID tower-sessions
+/// already picked is kept and this one is only used on a collision.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RandomIdGenerator;
+
+#[async_trait]
+impl IdGenerator for RandomIdGenerator {
+    async fn next_id(&self) -> session_store::Result<u128> {
+        Ok(Id::default().0 as u128)
+    }
+}
+
+/// Hands out ULIDs: the current time in milliseconds in the upper 48 bits
+/// and 80 random bits below, so that IDs sort by creation time. The time
+/// part keeps the top bit clear, so the resulting session IDs are
+/// positive.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UlidIdGenerator;
+
+#[async_trait]
+impl IdGenerator for UlidIdGenerator {
+    async fn next_id(&self) -> session_store::Result<u128> {
+        let milliseconds = (OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000) as u128;
+        let random = Id::default().0 as u128 & ((1 << 80) - 1);
+        Ok(((milliseconds & ((1 << 48) - 1)) << 80) | random)
+    }
+}
@@ -1,12 +1,53 @@
+//! A session store for `tower-sessions` backed by SurrealDB.
+//!
+//! Session IDs cover the full range of `tower_sessions::session::Id`,
+//! i.e. every `i128` value. Sessions are keyed by the decimal string of
+//! their ID so no conversion to a narrower integer type takes place; IDs
+//! handed out by `create` come from a counter and are always positive.
+//! Rows written by older versions of the store, keyed by an `i64`, are
+//! still found.
+//!
+//! The SurrealDB engines compiled in are picked with cargo features:
+//! `engine-any` (default) for `Surreal<Any>` and `new_from_nothing`,
+//! `engine-local` for in-process databases and `new_in_memory`,
+//! `rocksdb` for embedded databases persisted on disk and `new_rocksdb`,
+//! and `engine-remote` for WebSocket and HTTP connections only.
+//! There is no SpeeDB feature: SurrealDB 2 no longer ships that storage
+//! engine, so `rocksdb` is the only embedded engine persisted on disk.
+//!
+//! The store can sit behind `tower_sessions::CachingSessionStore`, e.g.
+//! with a `MokaStore` from `tower-sessions-moka-store` as the cache. The
+//! cache only learns about writes made through the caching store:
+//! `delete_expired` on the database, `delete_all_sessions_for_data_value`,
+//! `touch`, `patch_data` and writes from other application instances leave
+//! stale entries behind until they fall out of the cache. Use a short
+//! cache TTL, or call those methods only on stores that are not cached.
+
 use anyhow::Context;
-use surrealdb;
 use surrealdb::{
     Surreal
     , Connection
     , Datetime
-    , engine::any::Any
-    , opt::auth::Root
+    , opt::auth::Record as RecordCredentials
+    , RecordId as SurrealRecordId
+    , Action
+    , Notification
+    , Response
 };
+#[cfg(feature = "engine-any")]
+use surrealdb::{engine::any::Any, opt::auth::{Database, Namespace, Root}};
+#[cfg(feature = "engine-any")]
+use url::Url;
+#[cfg(feature = "engine-any")]
+use percent_encoding::percent_decode_str;
+#[cfg(feature = "engine-any")]
+use std::env::var;
+#[cfg(feature = "engine-local")]
+use surrealdb::engine::local::{Db, Mem};
+#[cfg(feature = "rocksdb")]
+use surrealdb::engine::local::RocksDb;
+#[cfg(any(feature = "rocksdb", feature = "snapshot"))]
+use std::path::Path;
 use tower_sessions::{
     ExpiredDeletion
     , SessionStore
@@ -22,38 +63,193 @@ use tower_sessions::{
             , EncodedConfig
         }
     }
-    , session_store::Error::{
-        Backend
-        , Encode
-        , Decode
-    }
     , session_store
 };
-use chrono;
 use serde::{Deserialize, Serialize};
 use std::{
-    env::var
+    borrow::Cow
+    , collections::{BTreeMap, HashMap, HashSet}
+    , future::IntoFuture
     , num::NonZeroU8
-    , fmt::Debug
+    , fmt::{self, Debug}
+    , sync::{
+        Arc
+        , Mutex
+        , PoisonError
+        , atomic::{AtomicU32, AtomicU64, Ordering}
+    }
+    , time::Duration as StdDuration
 };
+use tower_sessions::cookie::time::{OffsetDateTime, Duration, UtcOffset};
 use async_trait::async_trait;
+use serde_bytes::ByteBuf;
 use base64::{
     prelude::BASE64_STANDARD_NO_PAD
     , Engine
 };
-use tracing::debug;
+use tracing::{debug, error, info, warn};
+use tokio::{sync::watch, task::JoinHandle, time::MissedTickBehavior};
+use tokio::io::{
+    AsyncBufReadExt
+    , AsyncRead
+    , AsyncWrite
+    , AsyncWriteExt
+    , BufReader
+};
+use futures::{
+    Stream
+    , StreamExt
+    , TryStreamExt
+    , future::{self, Either}
+    , stream
+};
 
-#[cfg(test)]
+mod clock;
+mod error;
+mod id_generator;
+#[cfg(all(test, feature = "engine-any"))]
 mod tests;
 
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use error::{
+    ConnectionTestStep
+    , SurrealdbError
+    , TestConnectionError
+    , VERSION_CONFLICT_MARKER
+    , is_version_conflict
+};
+pub use id_generator::{
+    IdGenerator
+    , CounterIdGenerator
+    , UuidV4IdGenerator
+    , RandomIdGenerator
+    , UlidIdGenerator
+};
+
 const FORMAT_CONFIG: EncodedConfig = Config::DEFAULT.set_time_precision(
     TimePrecision::Second{decimal_digits: NonZeroU8::new(6)}
 ).encode();
 
+/// Formats an expiry date the way it is stored in the `expiry_date`
+/// column: UTC at microsecond precision, truncating anything finer. Every
+/// write path goes through here so that `load` (`expiry_date > time::now()`)
+/// and `delete_expired` (`expiry_date <= time::now()`) compare the same
+/// value whichever method wrote the session. A session is expired once its
+/// expiry date is at or before the current instant. Records may carry any
+/// offset, e.g. when built in local time; every date sent to SurrealDB is
+/// normalized here so that only the instant matters.
+fn format_expiry_date(expiry_date: OffsetDateTime) -> Result<String, SurrealdbError> {
+    truncate_expiry_date(expiry_date)?
+        .format(&Iso8601::<{FORMAT_CONFIG}>)
+        .map_err(|e| SurrealdbError::SerializationFailed(e.into()))
+}
+
+/// Converts a client-side time into a SurrealDB datetime at the precision
+/// of the `expiry_date` column.
+fn to_surreal_datetime(instant: OffsetDateTime) -> Result<Datetime, SurrealdbError> {
+    let chrono_datetime = format_expiry_date(instant)?.parse::<chrono::DateTime<chrono::offset::Utc>>()
+        .map_err(|e| SurrealdbError::SerializationFailed(e.into()))?;
+    Ok(Datetime::from(chrono_datetime))
+}
+
+/// The expiry date as it ends up in the `expiry_date` column.
+fn truncate_expiry_date(expiry_date: OffsetDateTime) -> Result<OffsetDateTime, SurrealdbError> {
+    let nanosecond = expiry_date.nanosecond();
+    expiry_date
+        .to_offset(UtcOffset::UTC)
+        .replace_nanosecond(nanosecond - nanosecond % 1_000)
+        .map_err(|e| SurrealdbError::SerializationFailed(e.into()))
+}
+
+/// Version of the data model layout created by `create_data_model`.
+/// Bump whenever tables, fields or indexes change.
+const SCHEMA_VERSION: u64 = 2;
+
+/// Data model version written by `create_data_model` to the
+/// `version` record of the `{sessions_table}_schema_meta` table, see
+/// `get_data_model_version`.
+pub const CURRENT_SCHEMA_VERSION: u32 = 1;
+
+/// Number of times `create` tries a fresh ID when the counter produced an
+/// ID that is already taken.
+const CREATE_ID_CONFLICT_ATTEMPTS: u32 = 10;
+
+/// Number of times `create` runs the counter transaction again when it
+/// conflicted with a concurrent create incrementing the counter.
+const COUNTER_CONFLICT_ATTEMPTS: u32 = 100;
+
+/// Part of the message SurrealDB fails a transaction with when it lost a
+/// read or write conflict and nothing was written.
+const RETRYABLE_CONFLICT: &str = "can be retried";
+
+/// Message thrown by `NEXT_COUNTER_VALUE` when the counter is out of range.
+const COUNTER_OUT_OF_RANGE: &str = "Session ID counter is out of range";
+
+/// Increments the ID counter and evaluates to the new value. A negative
+/// counter or one that would overflow is refused, e.g. after the counter
+/// was reset by hand, instead of handing out IDs that are zero, negative
+/// or fail deep inside SurrealDB.
+const NEXT_COUNTER_VALUE: &str = r#"{
+                LET $current = type::thing($counter_table, "counter").num ?? 0;
+                IF $current < 0 OR $current >= 9223372036854775807 {
+                    THROW "Session ID counter is out of range: " + <string> $current;
+                };
+                RETURN (UPSERT type::thing($counter_table, "counter") SET num += 1 RETURN AFTER)[0].num;
+            }"#;
+
+/// Number of sessions fetched per query by `export_all_sessions`.
+const EXPORT_CHUNK_SIZE: u64 = 1000;
+
+/// Number of rows copied per transaction by `relocate`.
+const RELOCATE_BATCH_SIZE: u64 = 1000;
+
+/// Number of sessions removed per transaction by `delete_all`.
+const DELETE_ALL_BATCH_SIZE: usize = 1000;
+
+/// Number of sessions removed per statement by `delete_many`.
+const DELETE_MANY_CHUNK_SIZE: usize = 1000;
+
+/// Number of sessions created per transaction by `import_sessions`.
+const IMPORT_CHUNK_SIZE: usize = 1000;
+
+/// Encoded session data above which `import_sessions` starts a new
+/// transaction before reaching `IMPORT_CHUNK_SIZE`, so that no query comes
+/// close to the WebSocket frame limit.
+const IMPORT_CHUNK_BYTES: usize = 4 << 20;
+
+/// Default for `SurrealdbStoreConfig::max_payload_bytes`. The WebSocket
+/// client rejects frames above 16 MiB with an opaque protocol error.
+const DEFAULT_MAX_PAYLOAD_BYTES: usize = 16 << 20;
+
+/// Lifetime of the session written by `test_connection`, kept short so
+/// that it expires soon should the final delete fail.
+const CONNECTION_TEST_TTL: Duration = Duration::seconds(10);
+
+/// Number of times `patch_data` and `remove_data_key` re-read a session
+/// that another write changed between reading and writing it.
+const PATCH_CONFLICT_ATTEMPTS: u32 = 10;
+
+/// Sessions are keyed by the decimal string of their i128 ID so that the
+/// full range of `Id` fits. Rows written by older versions of the store
+/// are keyed by a number, which is still understood.
 #[derive(Serialize, Deserialize)]
 #[serde(rename = "Id")]
 enum SurrealId {
     Number(i64)
+    , String(String)
+}
+
+impl TryFrom<SurrealId> for Id {
+    type Error = SurrealdbError;
+
+    fn try_from(surreal_id: SurrealId) -> Result<Id, SurrealdbError> {
+        match surreal_id {
+            SurrealId::Number(number) => Ok(Id(number.into()))
+            , SurrealId::String(string) => string.parse::<i128>()
+                .map(Id)
+                .map_err(|_| SurrealdbError::IdConversionFailed(string))
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -63,46 +259,734 @@ struct RecordId {
     , id: SurrealId
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 struct DatabaseRecord {
     #[serde(with = "serde_bytes")]
     record: Vec<u8>
     , expiry_date: Datetime
+    , #[serde(default, skip_serializing_if = "Option::is_none")]
+    user_key: Option<String>
 }
 
-impl TryFrom<&Record> for DatabaseRecord {
-    type Error = session_store::Error;
+/// A session row as read by `load`.
+#[derive(Deserialize)]
+struct LoadedDatabaseRecord {
+    /// only selected by `load_many`
+    #[serde(default)]
+    id: Option<RecordId>
+    , #[serde(with = "serde_bytes")]
+    record: Vec<u8>
+    , expiry_date: String
+    , #[serde(default)]
+    version: Option<u64>
+}
+
+/// A session row as read by `patch_data`, keeping the key it is stored
+/// under so that legacy rows are written back in place.
+#[derive(Deserialize)]
+struct PatchedDatabaseRecord {
+    id: SurrealRecordId
+    , #[serde(with = "serde_bytes")]
+    record: Vec<u8>
+}
+
+/// Format version prefixed to the bytes of every stored session so that
+/// the encoding can change without guessing what an old row holds.
+/// Version 2 is followed by a big endian CRC32 of the msgpack payload.
+/// Version 1 rows hold the msgpack payload right after the version byte
+/// and version 0 rows, written before records were versioned, are plain
+/// msgpack. Both are still read.
+const RECORD_FORMAT_VERSION: u8 = 0x02;
+
+/// Length of the version byte and checksum in front of the payload.
+const RECORD_HEADER_LENGTH: usize = 5;
+
+fn encode_record(record: &Record) -> Result<Vec<u8>, SurrealdbError> {
+    let mut bytes = vec![RECORD_FORMAT_VERSION, 0, 0, 0, 0];
+    // the loaded version is bookkeeping of the store, not session data
+    let record = match record.data.contains_key(SESSION_VERSION_KEY) {
+        true => {
+            let mut record = record.clone();
+            record.data.remove(SESSION_VERSION_KEY);
+            Cow::Owned(record)
+        }
+        , false => Cow::Borrowed(record)
+    };
+    rmp_serde::encode::write(&mut bytes, record.as_ref())
+        .map_err(|e| SurrealdbError::SerializationFailed(e.into()))?;
+    let checksum = crc32fast::hash(&bytes[RECORD_HEADER_LENGTH..]);
+    bytes[1..RECORD_HEADER_LENGTH].copy_from_slice(&checksum.to_be_bytes());
+    Ok(bytes)
+}
+
+/// Format version of stored session bytes. Unversioned rows start with
+/// the msgpack marker of the array `rmp_serde` writes `Record` as, or of
+/// a map, none of which are valid version bytes.
+fn record_format_version(bytes: &[u8]) -> Option<u8> {
+    match bytes.first() {
+        Some(0x80..=0x9f | 0xdc..=0xdf) => Some(0)
+        , version => version.copied()
+    }
+}
+
+fn decode_record(bytes: &[u8]) -> Result<Record, SurrealdbError> {
+    let Some(version) = record_format_version(bytes) else {
+        return Err(SurrealdbError::DeserializationFailed("Session record is empty".into()))
+    };
+    let msgpack = match version {
+        0 => {
+            warn!(
+                "Reading a session record without a format version. Support for these \
+                records is deprecated; saving the session rewrites it in format version {}"
+                , RECORD_FORMAT_VERSION
+            );
+            bytes
+        }
+        , 1 => &bytes[1..]
+        , 2 => {
+            if bytes.len() < RECORD_HEADER_LENGTH {
+                return Err(SurrealdbError::DeserializationFailed(format!(
+                    "Session record of format version {version} is {} bytes long, which is \
+                    shorter than its header"
+                    , bytes.len()
+                ).into()))
+            }
+            let (header, msgpack) = bytes.split_at(RECORD_HEADER_LENGTH);
+            let expected = u32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+            let actual = crc32fast::hash(msgpack);
+            if expected != actual {
+                return Err(SurrealdbError::ChecksumMismatch { version, expected, actual })
+            }
+            msgpack
+        }
+        , _ => return Err(SurrealdbError::UnknownRecordVersion(version))
+    };
+    rmp_serde::from_slice(msgpack)
+        .map_err(|e| SurrealdbError::DeserializationFailed(
+            format!("Session record of format version {version} is not valid msgpack: {e}").into()
+        ))
+}
 
-    fn try_from(record: &Record) -> session_store::Result<Self> {
-        let interim_datetime_string = record.expiry_date.format(&Rfc3339)
-            .map_err(|e| Encode(e.to_string()))?;
-        let chrono_datetime = interim_datetime_string.parse::<chrono::DateTime<chrono::offset::Utc>>()
-            .map_err(|e| Encode(e.to_string()))?;
+impl TryFrom<&Record> for DatabaseRecord {
+    type Error = SurrealdbError;
 
+    fn try_from(record: &Record) -> Result<Self, SurrealdbError> {
         Ok(Self {
-            record: rmp_serde::to_vec(record)
-                .map_err(|e| Encode(e.to_string()))?
-            , expiry_date: Datetime::from(chrono_datetime)
+            record: encode_record(record)?
+            , expiry_date: to_surreal_datetime(record.expiry_date)?
+            , user_key: None
         })
     }
 }
 
 impl TryFrom<DatabaseRecord> for Record {
-    type Error = session_store::Error;
+    type Error = SurrealdbError;
+
+    fn try_from(database_record: DatabaseRecord) -> Result<Record, SurrealdbError> {
+        decode_record(&database_record.record)
+    }
+}
+
+/// Metadata of a session that can be fetched without deserializing the
+/// session data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionMetadata {
+    pub expiry_date: OffsetDateTime,
+    pub id: Id
+}
+
+#[derive(Deserialize)]
+struct DatabaseMetadata {
+    expiry_date: String
+}
+
+/// Order in which `list_ids` pages through sessions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Sessions expiring first come first
+    #[default]
+    ExpiryAscending,
+    /// Sessions expiring last come first
+    ExpiryDescending
+}
+
+/// Which page of sessions `list_ids` returns. A `limit` of 0 returns an
+/// empty last page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pagination {
+    pub limit: u64,
+    pub offset: u64,
+    pub order: SortOrder
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Self {
+            limit: 50
+            , offset: 0
+            , order: SortOrder::ExpiryAscending
+        }
+    }
+}
+
+/// A page of results and the offset of the following page, None when
+/// this is the last one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_offset: Option<u64>
+}
+
+/// A session as listed by `list_ids`, without its data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionSummary {
+    pub id: Id,
+    pub expiry_date: OffsetDateTime,
+    /// Only recorded when `with_idempotent_create` was enabled as the
+    /// session was created, None otherwise even with `with_timestamps`
+    pub created_at: Option<OffsetDateTime>,
+    /// Only recorded when `with_timestamps` was enabled as the session was
+    /// last written
+    pub updated_at: Option<OffsetDateTime>
+}
+
+#[derive(Deserialize)]
+struct DatabaseSessionSummary {
+    id: RecordId
+    , expiry_date: String
+    , created_at: Option<String>
+    , updated_at: Option<String>
+}
+
+/// Describes the data model held by the database, as recorded by the
+/// last run of `create_data_model`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StoreMetadata {
+    pub schema_version: u64,
+    pub crate_version: String,
+    pub created_at: OffsetDateTime,
+    pub updated_at: OffsetDateTime
+}
+
+#[derive(Deserialize)]
+struct DatabaseStoreMetadata {
+    schema_version: u64
+    , crate_version: String
+    , created_at: String
+    , updated_at: String
+}
+
+impl TryFrom<DatabaseStoreMetadata> for StoreMetadata {
+    type Error = anyhow::Error;
+
+    fn try_from(database_metadata: DatabaseStoreMetadata) -> anyhow::Result<Self> {
+        Ok(Self {
+            schema_version: database_metadata.schema_version
+            , crate_version: database_metadata.crate_version
+            , created_at: OffsetDateTime::parse(&database_metadata.created_at, &Rfc3339)?
+            , updated_at: OffsetDateTime::parse(&database_metadata.updated_at, &Rfc3339)?
+        })
+    }
+}
+
+/// A change to a watched session, see `SurrealdbStore::watch_session`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SessionEvent {
+    Created(Record),
+    Updated(Record),
+    Deleted(Id)
+}
+
+/// Summary of what `relocate` did.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RelocateReport {
+    pub sessions_copied: u64,
+    pub archived_sessions_copied: u64,
+    pub counter_value: Option<i64>,
+    pub source_dropped: bool
+}
+
+/// A stored session whose record can't be decoded, as reported by
+/// `validate_record_integrity`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CorruptedSessionInfo {
+    pub id: Id,
+    pub error: String,
+    pub expiry_date: OffsetDateTime
+}
+
+/// Result of `save_if_unmodified_since`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveOutcome {
+    /// The session was unchanged since it was read and has been written
+    Saved,
+    /// The session was written by someone else since it was read and has
+    /// been left alone
+    Conflict {
+        current_updated_at: OffsetDateTime
+    }
+}
+
+/// How `create_data_model` applies the schema definitions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SchemaApplyMode {
+    /// Only define objects that don't exist yet. Existing definitions are
+    /// left untouched, even when they differ from what the store expects.
+    #[default]
+    IfNotExists,
+    /// Redefine every object with `DEFINE ... OVERWRITE` so that field
+    /// types and indexes are brought up to date. Data rows are never
+    /// touched. Requires SurrealDB 2.0 or newer.
+    Overwrite
+}
+
+impl SchemaApplyMode {
+    fn definition_clause(&self) -> &'static str {
+        match self {
+            SchemaApplyMode::IfNotExists => "IF NOT EXISTS"
+            , SchemaApplyMode::Overwrite => "OVERWRITE"
+        }
+    }
+}
+
+/// What `delete` does when there is no session with the given ID.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingDeleteBehavior {
+    /// Succeed without doing anything, as the `SessionStore` contract
+    /// expects.
+    #[default]
+    Ignore,
+    /// Return `SurrealdbError::MissingSession`, e.g. to surface clients
+    /// probing for session IDs.
+    Error
+}
+
+/// What `load` and the other reads do with a stored session that can't
+/// be decoded. Every such session is counted, see
+/// `SurrealdbStore::decode_failure_count`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecodeErrorBehavior {
+    /// Return `SurrealdbError::SessionDecodeFailed`
+    #[default]
+    Error,
+    /// Log the failure and delete the row, so that the client just gets a
+    /// new session. Streams like `export_sessions_chunked` skip the row
+    /// without deleting it
+    DeleteAndReturnNone,
+    /// Log the failure and treat the session as missing, leaving the row
+    /// in place for inspection
+    ReturnNone
+}
+
+/// How `create` picks the ID of a new session.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdAssignment {
+    /// Always replace the ID of the record with a new one from the counter
+    /// (or the configured ID generator).
+    #[default]
+    CounterAlways,
+    /// Keep the ID the record comes with, e.g. for migrations or test
+    /// fixtures, and only pick a new one when that ID is already taken.
+    HonorCallerThenCounter
+}
+
+/// Ready made combinations of `IdAssignment` and `IdGenerator`, set with
+/// `with_id_strategy`. Whatever the strategy, sessions are keyed by the
+/// decimal string of their ID, so `save`, `load` and `delete` find them
+/// the same way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IdStrategy {
+    /// IDs come from the counter in the sessions latest ID table
+    #[default]
+    Counter,
+    /// The random ID tower-sessions generated is kept, a new random one is
+    /// picked on a collision. The counter table is never touched.
+    Random,
+    /// Random version 4 UUIDs
+    Uuid,
+    /// ULIDs, sorting by creation time
+    Ulid
+}
+
+/// Summary of what `create_data_model` did. Each object the store needs
+/// is listed either under what was created or under `already_present`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DataModelReport {
+    pub tables_created: Vec<String>,
+    pub fields_created: Vec<String>,
+    pub indexes_created: Vec<String>,
+    pub already_present: Vec<String>
+}
+
+#[derive(Deserialize, Default)]
+struct DatabaseInfo {
+    #[serde(default)]
+    tables: HashMap<String, String>
+}
+
+#[derive(Deserialize, Default)]
+struct TableInfo {
+    #[serde(default)]
+    fields: HashMap<String, String>
+    , #[serde(default)]
+    indexes: HashMap<String, String>
+}
+
+/// Quotes an identifier (e.g. a table name) with backticks so that it
+/// can be safely interpolated into a query. Names containing a backtick
+/// or a null character are rejected since they can not be escaped.
+fn escape_identifier(identifier: &str) -> Result<String, SurrealdbError> {
+    if identifier.is_empty() || identifier.contains(['`', '\0']) {
+        return Err(SurrealdbError::InvalidIdentifier(identifier.into()))
+    }
+    Ok(format!("`{identifier}`"))
+}
+
+#[derive(Deserialize)]
+struct IdentifiedDatabaseRecord {
+    id: RecordId
+    , #[serde(with = "serde_bytes")]
+    record: Vec<u8>
+}
+
+/// Fingerprint of the data and expiry date of a session, used to find a
+/// recent identical `create`. The keys are sorted first so that it
+/// doesn't depend on the iteration order of the HashMap.
+fn create_fingerprint(record: &Record) -> Result<i64, SurrealdbError> {
+    let data: BTreeMap<&String, &serde_json::Value> = record.data.iter().collect();
+    let mut bytes = serde_json::to_vec(&data)
+        .map_err(|e| SurrealdbError::SerializationFailed(e.into()))?;
+    bytes.extend_from_slice(format_expiry_date(record.expiry_date)?.as_bytes());
+    Ok(crc32fast::hash(&bytes).into())
+}
+
+/// Gives a loaded session the expiry date of its column. The column wins
+/// when `touch` moved the expiry date; otherwise the record keeps the
+/// precision it was saved with.
+fn apply_stored_expiry_date(record: &mut Record, stored_expiry_date: &str) -> Result<(), SurrealdbError> {
+    let stored_expiry_date = OffsetDateTime::parse(stored_expiry_date, &Rfc3339)
+        .map_err(|e| SurrealdbError::DeserializationFailed(e.into()))?;
+    if truncate_expiry_date(record.expiry_date)? != stored_expiry_date {
+        record.expiry_date = stored_expiry_date;
+    }
+    Ok(())
+}
 
-    fn try_from(database_record: DatabaseRecord) -> session_store::Result<Record> {
-        rmp_serde::from_slice(&database_record.record).map_err(|e| Decode(e.to_string()))
+/// Extracts the type from a field definition as returned by `INFO FOR
+/// TABLE`, e.g. `option<string>` from `DEFINE FIELD user_key ON sessions
+/// TYPE option<string> PERMISSIONS FULL`. The type ends at the next
+/// keyword, which unlike types is upper case.
+fn field_type(definition: &str) -> Option<String> {
+    let (_, after_type) = definition.split_once(" TYPE ")?;
+    let field_type = after_type.split_whitespace()
+        .take_while(|word| !word.chars().all(|c| c.is_ascii_uppercase()))
+        .collect::<Vec<&str>>()
+        .join(" ");
+    (!field_type.is_empty()).then_some(field_type)
+}
+
+/// Looks up a dot separated path like `user.id` in session data.
+fn value_at_path<'a>(
+    data: &'a HashMap<String, serde_json::Value>
+    , json_path: &str
+) -> Option<&'a serde_json::Value> {
+    let mut keys = json_path.split('.');
+    let first = data.get(keys.next()?)?;
+    keys.try_fold(first, |current, key| current.get(key))
+}
+
+/// Turns a live query notification into a SessionEvent. Notifications
+/// whose record can not be decoded are logged and skipped.
+fn session_event(session_id: &Id, notification: Notification<DatabaseRecord>) -> Option<SessionEvent> {
+    let decode = |database_record: DatabaseRecord| {
+        Record::try_from(database_record)
+            .map(|mut record| {
+                record.id = *session_id;
+                record
+            })
+            .map_err(|e| warn!("Could not decode watched session {}: {}", session_id, e))
+            .ok()
+    };
+    match notification.action {
+        Action::Create => decode(notification.data).map(SessionEvent::Created)
+        , Action::Update => decode(notification.data).map(SessionEvent::Updated)
+        , Action::Delete => Some(SessionEvent::Deleted(*session_id))
+        , _ => None
+    }
+}
+
+/// Version a loaded session was last saved at by this store, with the
+/// expiry date it had then so that the entry can be dropped once the
+/// session is gone.
+#[derive(Clone, Copy, Debug)]
+struct TrackedVersion {
+    id: Id,
+    version: u64,
+    expiry_date: OffsetDateTime
+}
+
+/// Key of the session data that holds the version a session was loaded
+/// at when versioned saves are enabled, see `with_versioned_saves`. It is
+/// never written to the database.
+pub const SESSION_VERSION_KEY: &str = "__tower_sessions_surrealdb_store_version";
+
+/// What `SESSION_VERSION_KEY` holds: the loaded version and a token that
+/// is unique to the load, so that the saves of one load are told apart
+/// from the saves of another load of the same session.
+#[derive(Serialize, Deserialize)]
+struct LoadedVersion {
+    version: u64,
+    load: String
+}
+
+/// Counts an operation as in flight for as long as it is alive.
+struct InFlightGuard(Arc<AtomicU32>);
+
+impl InFlightGuard {
+    fn new(in_flight: &Arc<AtomicU32>) -> Self {
+        in_flight.fetch_add(1, Ordering::SeqCst);
+        Self(in_flight.clone())
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[derive(Deserialize)]
+struct TimestampedDatabaseRecord {
+    #[serde(with = "serde_bytes")]
+    record: Vec<u8>
+    , updated_at: String
+    , #[serde(default)]
+    version: Option<u64>
+}
+
+#[derive(Deserialize)]
+struct ExpiryCheckedDatabaseRecord {
+    #[serde(with = "serde_bytes")]
+    record: Vec<u8>
+    , expired: bool
+}
+
+#[derive(Deserialize)]
+struct IntegrityCheckRecord {
+    id: RecordId
+    , #[serde(with = "serde_bytes")]
+    record: Vec<u8>
+    , expiry_date: String
+}
+
+/// One line of the newline delimited JSON written by `backup_to_writer`.
+/// The ID is kept as a string as JSON numbers can't hold every i128.
+#[derive(Serialize, Deserialize)]
+struct BackupLine {
+    id: String,
+    expiry_date: String,
+    /// versioned msgpack encoded session record, base64 encoded
+    record: String
+}
+
+/// ID generator configured through `with_id_generator`.
+#[derive(Clone)]
+struct ConfiguredIdGenerator(Arc<dyn IdGenerator>);
+
+impl Debug for ConfiguredIdGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ConfiguredIdGenerator")
+    }
+}
+
+/// Clock configured through `with_clock`.
+#[derive(Clone)]
+struct ConfiguredClock(Arc<dyn Clock>);
+
+impl Debug for ConfiguredClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ConfiguredClock")
+    }
+}
+
+/// Function used to pull a user identifier out of a session record so
+/// that it can be stored in its own indexed column.
+type UserKeyFn = dyn Fn(&Record) -> Option<String> + Send + Sync;
+
+#[derive(Clone)]
+struct UserKeyExtractor(Arc<UserKeyFn>);
+
+impl Debug for UserKeyExtractor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("UserKeyExtractor")
+    }
+}
+
+/// Configuration of a SurrealdbStore, kept separate from the database
+/// connection so that it can be loaded from a config file with any serde
+/// based loader (config-rs, figment, serde_yaml, toml...). Fields that are
+/// left out take their default value. Credentials are deliberately not
+/// part of the configuration; the password is read from the DB_PASSWORD
+/// env var by `new_from_nothing`.
+///
+/// Example TOML config file:
+/// ```toml
+/// [session_store]
+/// sessions_table = "app_sessions"
+/// sessions_latest_id_table = "app_sessions_latest_id"
+/// namespace = "namespace"
+/// database = "database"
+/// reject_expired_on_create = true
+/// ```
+///
+/// Loading it and building the store:
+/// ```
+/// use serde::Deserialize;
+/// use surrealdb::{Surreal, engine::local::Mem};
+/// use tower_sessions_surrealdb_store::{SurrealdbStore, SurrealdbStoreConfig};
+///
+/// #[derive(Deserialize)]
+/// struct AppConfig {
+///     session_store: SurrealdbStoreConfig
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// # let config_toml = r#"
+/// # [session_store]
+/// # sessions_table = "app_sessions"
+/// # namespace = "namespace"
+/// # database = "database"
+/// # "#;
+/// let app_config: AppConfig = toml::from_str(config_toml)?;
+/// let my_surreal = Surreal::new::<Mem>(()).await?;
+/// let my_surreal_store = SurrealdbStore::from_config(app_config.session_store, my_surreal);
+/// my_surreal_store.ensure_namespace_and_database().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SurrealdbStoreConfig {
+    pub sessions_table: String,
+    pub sessions_latest_id_table: String,
+    /// Namespace used by `ensure_namespace_and_database`
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// Database used by `ensure_namespace_and_database`
+    #[serde(default)]
+    pub database: Option<String>,
+    /// When set, `delete_expired` moves expired sessions into this table
+    /// instead of deleting them
+    #[serde(default)]
+    pub archive_table: Option<String>,
+    /// When true, `create` and `save` refuse to write sessions whose
+    /// expiry date is already in the past
+    #[serde(default)]
+    pub reject_expired_on_create: bool,
+    /// Name of a record access (formerly scope). When set,
+    /// `create_data_model` adds permissions so that record users can only
+    /// read and write sessions whose `user_key` is their own record ID.
+    /// Record users get no access to the ID counter, so stores signed in
+    /// as one need an ID generator
+    #[serde(default)]
+    pub record_access: Option<String>,
+    /// When true, `save` returns an error for a session that isn't in the
+    /// store instead of creating it
+    #[serde(default)]
+    pub strict_save: bool,
+    /// What `delete` does for a session that isn't in the store
+    #[serde(default)]
+    pub missing_delete_behavior: MissingDeleteBehavior,
+    /// How `create` picks the ID of a new session
+    #[serde(default)]
+    pub id_assignment: IdAssignment,
+    /// What reads do with a stored session that can't be decoded
+    #[serde(default)]
+    pub on_decode_error: DecodeErrorBehavior,
+    /// When true, every session carries a version that `save` checks
+    /// against the one `load` returned, so that concurrent saves of the
+    /// same session conflict instead of overwriting each other
+    #[serde(default)]
+    pub versioned_saves: bool,
+    /// When true, `create_data_model` defines an `updated_at` field that
+    /// SurrealDB sets on every write, as used by `save_if_unmodified_since`
+    #[serde(default)]
+    pub timestamps: bool,
+    /// When true, `load` stamps a `last_accessed` field with the current
+    /// time, e.g. to find sessions nobody has used for a while
+    #[serde(default)]
+    pub track_access: bool,
+    /// Largest encoded session, in bytes, that `create` and `save` send to
+    /// the database. Larger sessions fail with
+    /// `SurrealdbError::SessionTooLarge` before any I/O. None disables the
+    /// check
+    pub max_payload_bytes: Option<usize>,
+    /// When set, `create` returns the ID of a session created within this
+    /// window with identical data and expiry date instead of creating
+    /// another one, e.g. for retried requests
+    #[serde(default)]
+    pub idempotent_create_window: Option<StdDuration>,
+    /// When true, queries compare expiry dates against the time of the
+    /// store's clock instead of SurrealDB's `time::now()`
+    #[serde(default)]
+    pub bind_now: bool,
+    /// When set, `create` gives sessions whose expiry date already passed
+    /// this long to live instead of writing them expired
+    #[serde(default)]
+    pub default_expiry: Option<StdDuration>
+}
+
+impl Default for SurrealdbStoreConfig {
+    fn default() -> Self {
+        Self {
+            sessions_table: "sessions".into()
+            , sessions_latest_id_table: "sessions_latest_id".into()
+            , namespace: None
+            , database: None
+            , archive_table: None
+            , reject_expired_on_create: false
+            , record_access: None
+            , strict_save: false
+            , missing_delete_behavior: MissingDeleteBehavior::Ignore
+            , id_assignment: IdAssignment::CounterAlways
+            , on_decode_error: DecodeErrorBehavior::Error
+            , versioned_saves: false
+            , timestamps: false
+            , track_access: false
+            , max_payload_bytes: Some(DEFAULT_MAX_PAYLOAD_BYTES)
+            , idempotent_create_window: None
+            , bind_now: false
+            , default_expiry: None
+        }
     }
 }
 
+/// Session store backed by SurrealDB.
+///
+/// Cloning the store is cheap: the Surreal client is reference counted
+/// internally, so every clone shares the same underlying connection. The
+/// store can therefore be passed by value into `SessionManagerLayer::new`
+/// without wrapping it in an `Arc`.
 #[derive(Clone, Debug)]
 pub struct SurrealdbStore<DB>
 where
     DB: Connection + Debug
 {
     client: Surreal<DB>,
-    sessions_table: String,
-    sessions_latest_id_table: String
+    config: SurrealdbStoreConfig,
+    user_key_extractor: Option<UserKeyExtractor>,
+    id_generator: Option<ConfiguredIdGenerator>,
+    clock: ConfiguredClock,
+    in_flight: Arc<AtomicU32>,
+    /// Version of every live session loaded or saved through this store
+    /// when versioned saves are enabled
+    session_versions: Arc<Mutex<HashMap<String, TrackedVersion>>>,
+    /// Number of stored sessions that could not be decoded
+    decode_failures: Arc<AtomicU64>
 }
 
 impl<DB> SurrealdbStore<DB>
@@ -111,8 +995,8 @@ where
 {
 
     /// Enables creating a new SurrealdbStore from a supplied Surreal
-    /// struct.
-    /// ```ignore
+    /// struct. No I/O is done so this is not async.
+    /// ```
     /// use anyhow;
     /// use surrealdb::{
     ///     Surreal
@@ -123,7 +1007,7 @@ where
     /// #[tokio::main]
     /// async fn main() -> anyhow::Result<()>{
     ///     let my_surreal: Surreal<Db> = Surreal::init();
-    ///     let my_client = my_surreal.connect::<Mem>(()).await?;
+    ///     my_surreal.connect::<Mem>(()).await?;
     ///     let my_surreal_store = SurrealdbStore::new(
     ///         my_surreal
     ///         , "sessions_table".into()
@@ -133,49 +1017,216 @@ where
     /// }
     /// ```
 
-    pub async fn new(
+    #[doc(alias = "new_async")]
+    pub fn new(
         client: Surreal<DB>
         , sessions_table: String
         , sessions_latest_id_table: String
     ) -> Self
     {
+        Self::from_config(
+            SurrealdbStoreConfig {
+                sessions_table: sessions_table
+                , sessions_latest_id_table: sessions_latest_id_table
+                , ..SurrealdbStoreConfig::default()
+            }
+            , client
+        )
+    }
+
+    /// Creates a new SurrealdbStore that signs in as a record user through
+    /// a record access (called a scope before SurrealDB 2.0) instead of as
+    /// root. Together with the permissions `create_data_model` defines
+    /// when a record access is configured, each session can then only be
+    /// read and written by the user it belongs to. The user key extractor
+    /// has to return the record ID of the user, e.g. `user:alice`, and an
+    /// ID generator such as `UuidV4IdGenerator` is needed as record users
+    /// may not write the ID counter.
+    /// ```
+    /// use anyhow;
+    /// use serde_json::json;
+    /// use surrealdb::{Surreal, engine::local::Mem};
+    /// use tower_sessions_surrealdb_store::SurrealdbStore;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> anyhow::Result<()>{
+    ///     let my_surreal = Surreal::new::<Mem>(()).await?;
+    ///     my_surreal.use_ns("namespace").use_db("database").await?;
+    ///     my_surreal.query(r#"
+    ///         DEFINE ACCESS user_access ON DATABASE TYPE RECORD
+    ///             SIGNIN (
+    ///                 SELECT * FROM user
+    ///                 WHERE email = $email
+    ///                 AND crypto::argon2::compare(password, $password)
+    ///             );
+    ///         CREATE user:alice SET
+    ///             email = "alice@example.com"
+    ///             , password = crypto::argon2::generate("secret");
+    ///     "#).await?.check()?;
+    ///     let my_surreal_store = SurrealdbStore::new_with_scope(
+    ///         my_surreal
+    ///         , "user_access"
+    ///         , json!({"email": "alice@example.com", "password": "secret"})
+    ///         , "namespace"
+    ///         , "database"
+    ///         , "sessions".into()
+    ///         , "sessions_latest_id".into()
+    ///     ).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Platform-specific notes
+    ///
+    /// With a SurrealDB server the record access and users are usually
+    /// defined ahead of time and the client connects over WebSocket.
+    /// ```no_run
+    /// use anyhow;
+    /// use serde_json::json;
+    /// use surrealdb::{Surreal, engine::any::Any};
+    /// use tower_sessions_surrealdb_store::SurrealdbStore;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> anyhow::Result<()>{
+    ///     let my_surreal: Surreal<Any> = Surreal::init();
+    ///     my_surreal.connect("ws://localhost:8000").await?;
+    ///     let my_surreal_store = SurrealdbStore::new_with_scope(
+    ///         my_surreal
+    ///         , "user_access"
+    ///         , json!({"email": "alice@example.com", "password": "..."})
+    ///         , "namespace"
+    ///         , "database"
+    ///         , "sessions".into()
+    ///         , "sessions_latest_id".into()
+    ///     ).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+
+    pub async fn new_with_scope<P>(
+        client: Surreal<DB>
+        , scope: &str
+        , params: P
+        , namespace: &str
+        , database: &str
+        , sessions_table: String
+        , sessions_latest_id_table: String
+    ) -> anyhow::Result<Self>
+    where
+        P: Serialize + Send + Sync + 'static
+    {
+        client.signin(RecordCredentials {
+            namespace: namespace
+            , database: database
+            , access: scope
+            , params: params
+        }).await.context(format!("Could not sign in through record access {scope}.\n\
+            Namespace was {namespace}.\n\
+            Database was {database}"
+        ))?;
+        client.use_ns(namespace).use_db(database).await
+            .context(format!("Check that the names or the namespace and database are correct\n\
+                that they exist.\n\
+                Namespace was {namespace}.\n\
+                Database was {database}"
+            ))?;
+        Ok(Self::from_config(
+            SurrealdbStoreConfig {
+                sessions_table: sessions_table
+                , sessions_latest_id_table: sessions_latest_id_table
+                , namespace: Some(namespace.into())
+                , database: Some(database.into())
+                , record_access: Some(scope.into())
+                , ..SurrealdbStoreConfig::default()
+            }
+            , client
+        ))
+    }
+
+    /// Creates a new SurrealdbStore from a configuration and a supplied
+    /// Surreal struct. This is the primary constructor; the others
+    /// delegate to it.
+    /// ```
+    /// use anyhow;
+    /// use surrealdb::{
+    ///     Surreal
+    ///     , engine::local::{Db, Mem}
+    /// };
+    /// use tower_sessions_surrealdb_store::{SurrealdbStore, SurrealdbStoreConfig};
+    /// 
+    /// #[tokio::main]
+    /// async fn main() -> anyhow::Result<()>{
+    ///     let my_surreal: Surreal<Db> = Surreal::init();
+    ///     my_surreal.connect::<Mem>(()).await?;
+    ///     let my_surreal_store = SurrealdbStore::from_config(
+    ///         SurrealdbStoreConfig::default()
+    ///         , my_surreal
+    ///     );
+    ///     Ok(())
+    /// }
+    /// ```
+
+    pub fn from_config(config: SurrealdbStoreConfig, client: Surreal<DB>) -> Self {
         Self {
             client: client
-            , sessions_table: sessions_table
-            , sessions_latest_id_table: sessions_latest_id_table
+            , config: config
+            , user_key_extractor: None
+            , id_generator: None
+            , clock: ConfiguredClock(Arc::new(SystemClock))
+            , in_flight: Arc::new(AtomicU32::new(0))
+            , session_versions: Arc::new(Mutex::new(HashMap::new()))
+            , decode_failures: Arc::new(AtomicU64::new(0))
         }
     }
     
     /// Creates the data model in the database to support the store.
+    /// Returns a DataModelReport listing which objects were created and
+    /// which were already present. With `SchemaApplyMode::Overwrite` the
+    /// objects that were already present are redefined as well.
+    ///
+    /// When a record access is configured, table and field permissions are
+    /// defined so that record users only reach their own sessions. The
+    /// `DEFINE ACCESS` statement itself is left to the application since
+    /// its SIGNIN and SIGNUP clauses depend on the application's user
+    /// table. Run this with root credentials.
+    ///
+    /// An index is defined on `expiry_date`. It speeds up `delete_expired`
+    /// and the expiry check done by `load` on large tables at the cost of
+    /// some extra work on every `create` and `save`.
     /// 
     /// Example code for memory database
-    /// ```ignore
+    /// ```
     /// use anyhow;
     /// use surrealdb::{
     ///     Surreal
     ///     , engine::local::{Db, Mem}
     /// };
-    /// use tower_sessions_surrealdb_store::SurrealdbStore;
+    /// use tower_sessions_surrealdb_store::{SchemaApplyMode, SurrealdbStore};
     /// 
     /// #[tokio::main]
     /// async fn main() -> anyhow::Result<()>{
     ///     let my_surreal: Surreal<Db> = Surreal::init();
-    ///     let my_client = my_surreal.connect::<Mem>(()).await?;
+    ///     my_surreal.connect::<Mem>(()).await?;
+    ///     my_surreal.use_ns("namespace").use_db("database").await?;
     ///     let my_surreal_store = SurrealdbStore::new(
     ///         my_surreal
     ///         , "sessions_table".into()
     ///         , "sessions_latest_id_table".into()
-    ///     ).await;
-    ///     my_surreal_store.create_data_model().await?;
+    ///     );
+    ///     my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
     ///     Ok(())
     /// }
     /// ```
-    /// 
-    /// Example code for rocksdb based database
-    /// ```ignore
+    ///
+    /// # Platform-specific notes
+    ///
+    /// Against a SurrealDB server the same call works on a store connected
+    /// over WebSocket. This needs a server listening on `localhost:8000`
+    /// and the root password in the DB_PASSWORD env var.
+    /// ```no_run
     /// use anyhow;
     /// use surrealdb::engine::any::Any;
-    /// use tower_sessions_surrealdb_store::SurrealdbStore;
+    /// use tower_sessions_surrealdb_store::{SchemaApplyMode, SurrealdbStore};
     /// #[tokio::main]
     /// async fn main() -> anyhow::Result<()>{
     ///     let my_surreal_store: SurrealdbStore<Any> = SurrealdbStore::new_from_nothing(
@@ -186,36 +1237,4209 @@ where
     ///         , "database".into()
     ///         , "sessions".into()
     ///         , "sessions_latest_id".into()
+    ///         , false
     ///     ).await?;
-    ///     my_surreal_store.create_data_model().await?;
+    ///     my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
     ///     Ok(())
     /// }
     /// ```
 
-    pub async fn create_data_model(&self) -> anyhow::Result<()> {
+    pub async fn create_data_model(
+        &self
+        , mode: SchemaApplyMode
+    ) -> anyhow::Result<DataModelReport> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        // SurrealDB does not accept bound parameters for identifiers in
+        // DEFINE statements so the names are escaped instead.
+        let sessions_table = escape_identifier(&self.config.sessions_table)?;
+        let user_key_index_name = format!("{}_user_key_index", self.config.sessions_table);
+        let user_key_index = escape_identifier(&user_key_index_name)?;
+        let expiry_date_index_name = format!("{}_expiry_date_index", self.config.sessions_table);
+        let expiry_date_index = escape_identifier(&expiry_date_index_name)?;
+        let (table_existed, fields_before, indexes_before) = self.data_model_info().await?;
+        // in record access mode each record user may only touch the sessions
+        // whose user_key is their own record ID. Record users get no access
+        // to the counter table at all, as anyone able to write it could
+        // break or steer the IDs of everybody else, so they need an ID
+        // generator instead.
+        let (table_permissions, user_key_permissions, counter_table) = match self.config.record_access {
+            Some(_) => (
+                "PERMISSIONS FOR select, create, update, delete WHERE user_key = <string> $auth.id"
+                , "PERMISSIONS FOR create, update WHERE $value = <string> $auth.id"
+                , format!(
+                    "DEFINE TABLE {} {} SCHEMALESS PERMISSIONS NONE;"
+                    , mode.definition_clause()
+                    , escape_identifier(&self.config.sessions_latest_id_table)?
+                )
+            )
+            , None => ("", "", String::new())
+        };
+        let version_field = match self.config.versioned_saves {
+            true => format!(
+                "DEFINE FIELD {} version ON TABLE {} TYPE option<int>;"
+                , mode.definition_clause()
+                , sessions_table
+            )
+            , false => String::new()
+        };
+        let updated_at_field = match self.config.timestamps {
+            true => format!(
+                "DEFINE FIELD {} updated_at ON TABLE {} TYPE option<datetime> VALUE time::now();"
+                , mode.definition_clause()
+                , sessions_table
+            )
+            , false => String::new()
+        };
+        let last_accessed_field = match self.config.track_access {
+            true => format!(
+                "DEFINE FIELD {} last_accessed ON TABLE {} TYPE option<datetime>;"
+                , mode.definition_clause()
+                , sessions_table
+            )
+            , false => String::new()
+        };
+        let create_fingerprint_index_name = format!("{}_create_fingerprint_index", self.config.sessions_table);
+        let create_fingerprint_fields = match self.config.idempotent_create_window {
+            Some(_) => format!(
+                "DEFINE FIELD {0} create_fingerprint ON TABLE {1} TYPE option<int>;
+                DEFINE FIELD {0} created_at ON TABLE {1} TYPE option<datetime>;
+                DEFINE INDEX {0} {2} ON TABLE {1} FIELDS create_fingerprint;"
+                , mode.definition_clause()
+                , sessions_table
+                , escape_identifier(&create_fingerprint_index_name)?
+            )
+            , None => String::new()
+        };
         let creation_query = format!(r"
                 BEGIN TRANSACTION;
-                DEFINE TABLE IF NOT EXISTS {0} SCHEMAFULL;
-                DEFINE FIELD IF NOT EXISTS id ON TABLE {0} TYPE int;
-                DEFINE FIELD IF NOT EXISTS expiry_date ON TABLE {0} TYPE datetime;
-                DEFINE FIELD IF NOT EXISTS record ON TABLE {0} TYPE bytes;
+                DEFINE TABLE {2} {0} SCHEMAFULL {4};
+                DEFINE FIELD {2} id ON TABLE {0} TYPE int | string;
+                DEFINE FIELD {2} expiry_date ON TABLE {0} TYPE datetime;
+                DEFINE FIELD {2} record ON TABLE {0} TYPE bytes;
+                DEFINE FIELD {2} user_key ON TABLE {0} TYPE option<string> {5};
+                DEFINE INDEX {2} {1} ON TABLE {0} FIELDS user_key;
+                DEFINE INDEX {2} {3} ON TABLE {0} FIELDS expiry_date;
+                {6}
+                {7}
+                {8}
+                {9}
+                {10}
                 COMMIT TRANSACTION;
-            ", self.sessions_table);
-        self.client.query(creation_query)
-            .await?;
-        Ok(())
-    }
-}
-
-impl SurrealdbStore<Any> {
+            ", sessions_table
+            , user_key_index
+            , mode.definition_clause()
+            , expiry_date_index
+            , table_permissions
+            , user_key_permissions
+            , counter_table
+            , version_field
+            , updated_at_field
+            , last_accessed_field
+            , create_fingerprint_fields
+        );
+        let creation_result = self.client.query(creation_query)
+            .await
+            .and_then(|response| response.check());
+        if let Err(e) = creation_result {
+            if mode == SchemaApplyMode::Overwrite && e.to_string().contains("OVERWRITE") {
+                return Err(anyhow::anyhow!(e).context("SchemaApplyMode::Overwrite requires \
+                    SurrealDB 2.0 or newer. Upgrade the server or use SchemaApplyMode::IfNotExists."
+                ))
+            }
+            return Err(e.into())
+        }
+        let (table_exists, fields_after, indexes_after) = self.data_model_info().await?;
 
-    /// Enables creating a SurrealdbStore<Any> instance from nothing.
-    /// To use this please make sure you configure your env variable
+        let mut report = DataModelReport::default();
+        if table_existed {
+            report.already_present.push(self.config.sessions_table.clone());
+        } else if table_exists {
+            report.tables_created.push(self.config.sessions_table.clone());
+        }
+        for field in [
+            "id"
+            , "expiry_date"
+            , "record"
+            , "user_key"
+            , "version"
+            , "updated_at"
+            , "last_accessed"
+            , "create_fingerprint"
+            , "created_at"
+        ] {
+            if fields_before.contains_key(field) {
+                report.already_present.push(field.into());
+            } else if fields_after.contains_key(field) {
+                report.fields_created.push(field.into());
+            }
+        }
+        for index in [user_key_index_name, expiry_date_index_name, create_fingerprint_index_name] {
+            if indexes_before.contains_key(&index) {
+                report.already_present.push(index);
+            } else if indexes_after.contains_key(&index) {
+                report.indexes_created.push(index);
+            }
+        }
+        if let Some(archive_table) = &self.config.archive_table {
+            let archive_query = format!(r"
+                    BEGIN TRANSACTION;
+                    DEFINE TABLE {1} {0} SCHEMAFULL;
+                    DEFINE FIELD {1} id ON TABLE {0} TYPE int | string;
+                    DEFINE FIELD {1} expiry_date ON TABLE {0} TYPE datetime;
+                    DEFINE FIELD {1} record ON TABLE {0} TYPE bytes;
+                    DEFINE FIELD {1} user_key ON TABLE {0} TYPE option<string>;
+                    DEFINE FIELD {1} archived_at ON TABLE {0} TYPE datetime;
+                    COMMIT TRANSACTION;
+                ", escape_identifier(archive_table)?
+                , mode.definition_clause()
+            );
+            self.client.query(archive_query)
+                .await?
+                .check()?;
+        }
+        self.client.query(r#"
+                UPSERT type::thing($table, "meta") SET
+                    schema_version = $schema_version
+                    , crate_version = $crate_version
+                    , created_at = created_at ?? time::now()
+                    , updated_at = time::now();
+            "#).bind(("table", self.config.sessions_latest_id_table.clone()))
+            .bind(("schema_version", SCHEMA_VERSION))
+            .bind(("crate_version", env!("CARGO_PKG_VERSION")))
+            .await?
+            .check()?;
+        // a version that is already recorded is never overwritten
+        self.client.query(r#"
+                UPSERT type::thing($table, "version") SET version = version ?? $version;
+            "#).bind(("table", self.schema_meta_table()))
+            .bind(("version", CURRENT_SCHEMA_VERSION))
+            .await?
+            .check()?;
+        Ok(report)
+    }
+
+    /// **Destroys every session.** Removes the sessions table and the ID
+    /// counter table together with their fields, indexes and schema
+    /// metadata, in one transaction. It is meant for resetting a database
+    /// between tests or from admin tooling, never for a running
+    /// application. Call `create_data_model` to start over afterwards.
+    /// The archive table, if any, is left alone.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// my_surreal_store.wipe_data_model().await?;
+    /// my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn wipe_data_model(&self) -> anyhow::Result<()> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        self.client.query(format!(r"
+                BEGIN TRANSACTION;
+                REMOVE TABLE IF EXISTS {0};
+                REMOVE TABLE IF EXISTS {1};
+                REMOVE TABLE IF EXISTS {2};
+                COMMIT TRANSACTION;
+            ", escape_identifier(&self.config.sessions_table)?
+            , escape_identifier(&self.config.sessions_latest_id_table)?
+            , escape_identifier(&self.schema_meta_table())?
+            ))
+            .await?
+            .check()
+            .context("Could not remove the session tables")?;
+        self.session_versions().clear();
+        warn!(
+            "Removed the session tables {} and {}"
+            , self.config.sessions_table
+            , self.config.sessions_latest_id_table
+        );
+        Ok(())
+    }
+
+    /// Points the store at a different sessions table.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let my_surreal_store = my_surreal_store.with_sessions_table("app_sessions".into());
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_sessions_table(mut self, sessions_table: String) -> Self {
+        self.config.sessions_table = sessions_table;
+        self
+    }
+
+    /// Moves the ID counter to the highest session ID it could have handed
+    /// out, i.e. the highest ID in the sessions table between 1 and
+    /// `i64::MAX`, or 0 for a table without such IDs. Use it when `create`
+    /// fails with `SurrealdbError::CounterOutOfRange`, e.g. after the
+    /// counter was reset by hand. Returns the new counter value.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let counter = my_surreal_store.reset_counter().await?;
+    /// # assert_eq!(counter, 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn reset_counter(&self) -> anyhow::Result<i64> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let record_ids: Vec<RecordId> = self.client
+            .query("select value id from type::table($table)")
+            .bind(("table", self.config.sessions_table.clone()))
+            .await?
+            .check()?
+            .take(0)?;
+        let counter = record_ids.into_iter()
+            .filter_map(|record_id| Id::try_from(record_id.id).ok())
+            .filter_map(|id| i64::try_from(id.0).ok())
+            .filter(|id| *id > 0)
+            .max()
+            .unwrap_or(0);
+        self.client.query(r#"UPSERT type::thing($counter_table, "counter") SET num = $counter;"#)
+            .bind(("counter_table", self.config.sessions_latest_id_table.clone()))
+            .bind(("counter", counter))
+            .await?
+            .check()
+            .context("Could not reset the ID counter")?;
+        Ok(counter)
+    }
+
+    /// Points the store at a different counter table.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let my_surreal_store = my_surreal_store
+    ///     .with_sessions_latest_id_table("app_sessions_latest_id".into());
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_sessions_latest_id_table(mut self, sessions_latest_id_table: String) -> Self {
+        self.config.sessions_latest_id_table = sessions_latest_id_table;
+        self
+    }
+
+    /// Name of the table the sessions are stored in.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// assert_eq!(my_surreal_store.sessions_table_name(), "sessions");
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn sessions_table_name(&self) -> &str {
+        &self.config.sessions_table
+    }
+
+    /// Name of the table holding the ID counter.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// assert_eq!(my_surreal_store.sessions_id_table_name(), "sessions_latest_id");
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn sessions_id_table_name(&self) -> &str {
+        &self.config.sessions_latest_id_table
+    }
+
+    /// Moves all sessions and the ID counter into new tables. The target
+    /// schema is created first, then the sessions are copied in batches,
+    /// one transaction per batch, with every stored field, and the row
+    /// counts are verified. In archive mode the archived sessions are
+    /// moved into `new_archive_table` the same way, which is then
+    /// required. With `drop_source` the old tables are removed afterwards.
+    /// The store itself keeps pointing at the old tables; use
+    /// `with_sessions_table`, `with_sessions_latest_id_table` and
+    /// `with_archive_table` to re-point it.
+    ///
+    /// Sessions created while the relocation runs may not be copied, so
+    /// stop traffic to the store first.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let report = my_surreal_store
+    ///     .relocate("app_sessions", "app_sessions_latest_id", None, false)
+    ///     .await?;
+    /// let my_surreal_store = my_surreal_store
+    ///     .with_sessions_table("app_sessions".into())
+    ///     .with_sessions_latest_id_table("app_sessions_latest_id".into());
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn relocate(
+        &self
+        , new_sessions_table: &str
+        , new_counter_table: &str
+        , new_archive_table: Option<&str>
+        , drop_source: bool
+    ) -> anyhow::Result<RelocateReport> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let archive_tables = match (&self.config.archive_table, new_archive_table) {
+            (Some(archive_table), Some(new_archive_table)) => Some((archive_table.as_str(), new_archive_table))
+            , (Some(archive_table), None) => anyhow::bail!(
+                "Archive mode is enabled, so relocate needs a table to move {archive_table} into"
+            )
+            , (None, _) => None
+        };
+        let mut target = self.clone()
+            .with_sessions_table(new_sessions_table.into())
+            .with_sessions_latest_id_table(new_counter_table.into());
+        if let Some((_, new_archive_table)) = archive_tables {
+            target = target.with_archive_table(new_archive_table.into());
+        }
+        target.create_data_model(SchemaApplyMode::IfNotExists).await
+            .context("Could not create the data model for the target tables")?;
+
+        let sessions_copied = self.copy_rows(&self.config.sessions_table, new_sessions_table).await?;
+        let archived_sessions_copied = match archive_tables {
+            Some((archive_table, new_archive_table)) => self.copy_rows(archive_table, new_archive_table).await?
+            , None => 0
+        };
+
+        let mut response = self.client.query(r#"
+                UPSERT type::thing($target, "counter") SET
+                    num = type::thing($source, "counter").num
+                RETURN VALUE num;
+            "#).bind(("source", self.config.sessions_latest_id_table.clone()))
+            .bind(("target", new_counter_table.to_string()))
+            .await?
+            .check()
+            .context("Could not copy the ID counter")?;
+        let counter_value: Option<i64> = response.take(0)?;
+
+        if drop_source {
+            let mut source_tables = vec![
+                escape_identifier(&self.config.sessions_table)?
+                , escape_identifier(&self.config.sessions_latest_id_table)?
+            ];
+            if let Some((archive_table, _)) = archive_tables {
+                source_tables.push(escape_identifier(archive_table)?);
+            }
+            let removals: String = source_tables.iter()
+                .map(|table| format!("REMOVE TABLE {table};"))
+                .collect();
+            self.client.query(format!("BEGIN TRANSACTION; {removals} COMMIT TRANSACTION;"))
+                .await?
+                .check()
+                .context("Sessions were relocated but the source tables could not be removed")?;
+        }
+        Ok(RelocateReport {
+            sessions_copied
+            , archived_sessions_copied
+            , counter_value: counter_value
+            , source_dropped: drop_source
+        })
+    }
+
+    /// Copies every row of `source_table` into `target_table` for
+    /// `relocate`, keeping all fields and the keys, and verifies the count.
+    async fn copy_rows(&self, source_table: &str, target_table: &str) -> anyhow::Result<u64> {
+        let source_count = self.count_rows(source_table).await?;
+        let mut start = 0;
+        while start < source_count {
+            self.client.query(format!(r"
+                    BEGIN TRANSACTION;
+                    INSERT INTO {1} (
+                        select
+                            *
+                            , record::id(id) as id
+                        from {0}
+                        order by id
+                        limit $limit
+                        start $start
+                    );
+                    COMMIT TRANSACTION;
+                ", escape_identifier(source_table)?
+                , escape_identifier(target_table)?
+                ))
+                .bind(("limit", RELOCATE_BATCH_SIZE))
+                .bind(("start", start))
+                .await?
+                .check()
+                .context(format!("Could not copy {source_table} starting at row {start}"))?;
+            start += RELOCATE_BATCH_SIZE;
+        }
+        let target_count = self.count_rows(target_table).await?;
+        if target_count != source_count {
+            anyhow::bail!(
+                "Relocation copied {target_count} rows into {target_table} but {source_table} \
+                holds {source_count}"
+            )
+        }
+        Ok(target_count)
+    }
+
+    async fn count_rows(&self, table: &str) -> anyhow::Result<u64> {
+        let mut response = self.client.query(r#"
+                select count() as count
+                from type::table($table)
+                group all
+            "#).bind(("table", table.to_string()))
+            .await?
+            .check()?;
+        let count: Option<u64> = response.take((0, "count"))?;
+        Ok(count.unwrap_or(0))
+    }
+
+    /// Enables archive mode. Instead of deleting expired sessions,
+    /// `delete_expired` moves them into the given table, stamped with an
+    /// `archived_at` datetime. Archived sessions are never returned by
+    /// `load`. Use `purge_archive` to remove old entries for good.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let my_surreal_store = my_surreal_store.with_archive_table("sessions_archive".into());
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_archive_table(mut self, archive_table: String) -> Self {
+        self.config.archive_table = Some(archive_table);
+        self
+    }
+
+    /// Sets the record access that `create_data_model` defines the per-user
+    /// permissions for. Use this on the root authenticated store that sets
+    /// up the data model for stores created with `new_with_scope`. Record
+    /// users can't write the ID counter table, so the stores they use need
+    /// an ID generator.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let my_surreal_store = my_surreal_store.with_record_access("user_access".into());
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_record_access(mut self, record_access: String) -> Self {
+        self.config.record_access = Some(record_access);
+        self
+    }
+
+    /// Makes `save` return an error for a session that isn't in the store.
+    /// By default such a session is created.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let my_surreal_store = my_surreal_store.with_strict_save(true);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_strict_save(mut self, strict_save: bool) -> Self {
+        self.config.strict_save = strict_save;
+        self
+    }
+
+    /// Picks how new sessions get their ID, replacing both the
+    /// `IdAssignment` and any ID generator set before.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let my_surreal_store = my_surreal_store.with_id_strategy(IdStrategy::Random);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_id_strategy(self, id_strategy: IdStrategy) -> Self {
+        let mut store = match id_strategy {
+            IdStrategy::Counter => Self {
+                id_generator: None
+                , ..self
+            }
+            , IdStrategy::Random => self.with_id_generator(RandomIdGenerator)
+            , IdStrategy::Uuid => self.with_id_generator(UuidV4IdGenerator)
+            , IdStrategy::Ulid => self.with_id_generator(UlidIdGenerator)
+        };
+        store.config.id_assignment = match id_strategy {
+            IdStrategy::Random => IdAssignment::HonorCallerThenCounter
+            , _ => IdAssignment::CounterAlways
+        };
+        store
+    }
+
+    /// Sets how `create` picks the ID of a new session. By default the ID
+    /// the record comes with is always replaced.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let my_surreal_store = my_surreal_store
+    ///     .with_id_assignment(IdAssignment::HonorCallerThenCounter);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_id_assignment(mut self, id_assignment: IdAssignment) -> Self {
+        self.config.id_assignment = id_assignment;
+        self
+    }
+
+    /// Sets what `load`, `load_ignoring_expiry`, `load_with_updated_at`
+    /// and `export_sessions_chunked` do with a stored session that can't be
+    /// decoded. By default an error is returned, on every request carrying
+    /// that session's cookie.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let my_surreal_store = my_surreal_store
+    ///     .with_on_decode_error(DecodeErrorBehavior::DeleteAndReturnNone);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_on_decode_error(mut self, on_decode_error: DecodeErrorBehavior) -> Self {
+        self.config.on_decode_error = on_decode_error;
+        self
+    }
+
+    /// Returns how many stored sessions could not be decoded since the
+    /// store was created, across every clone of it, whatever
+    /// `on_decode_error` did with them. Export it as a metric to notice
+    /// corruption that lenient decoding would otherwise hide.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// let corrupted_sessions = my_surreal_store.decode_failure_count();
+    /// # assert_eq!(corrupted_sessions, 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn decode_failure_count(&self) -> u64 {
+        self.decode_failures.load(Ordering::Relaxed)
+    }
+
+    /// Counts a session that could not be decoded and applies
+    /// `on_decode_error` to it. Returns Ok when the session is to be
+    /// treated as missing. Rows are only deleted when `may_delete` is set.
+    async fn skip_undecodable(
+        &self
+        , session_id: &Id
+        , length: usize
+        , source: SurrealdbError
+        , may_delete: bool
+    ) -> session_store::Result<()> {
+        self.decode_failures.fetch_add(1, Ordering::Relaxed);
+        let error = SurrealdbError::SessionDecodeFailed {
+            id: *session_id
+            , length
+            , source: Box::new(source)
+        };
+        match self.config.on_decode_error {
+            DecodeErrorBehavior::Error => Err(error.into())
+            , DecodeErrorBehavior::DeleteAndReturnNone if may_delete => {
+                warn!("{}. Deleting it.", error);
+                self.delete_returning(session_id).await?;
+                Ok(())
+            }
+            , DecodeErrorBehavior::ReturnNone | DecodeErrorBehavior::DeleteAndReturnNone => {
+                warn!("{}", error);
+                Ok(())
+            }
+        }
+    }
+
+    /// Sets what `delete` does for a session that isn't in the store. By
+    /// default nothing happens.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let my_surreal_store = my_surreal_store
+    ///     .with_missing_delete_behavior(MissingDeleteBehavior::Error);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_missing_delete_behavior(
+        mut self
+        , missing_delete_behavior: MissingDeleteBehavior
+    ) -> Self {
+        self.config.missing_delete_behavior = missing_delete_behavior;
+        self
+    }
+
+    /// Makes `create` and `save` return an error instead of writing a
+    /// session whose expiry date is already in the past. Off by default.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let my_surreal_store = my_surreal_store.with_reject_expired_on_create(true);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_reject_expired_on_create(mut self, reject_expired_on_create: bool) -> Self {
+        self.config.reject_expired_on_create = reject_expired_on_create;
+        self
+    }
+
+    /// Makes `create` replace an expiry date which already passed with the
+    /// current time plus `duration`, logging a warning, e.g. for callers
+    /// that build records with a zeroed date by accident. This takes
+    /// precedence over `with_reject_expired_on_create` for creates; saves
+    /// are not affected.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// let my_surreal_store = my_surreal_store
+    ///     .with_default_expiry_duration(std::time::Duration::from_secs(3600));
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_default_expiry_duration(mut self, duration: StdDuration) -> Self {
+        self.config.default_expiry = Some(duration);
+        self
+    }
+
+    /// Enables optimistic concurrency control for `save`. Every session
+    /// then carries a version that is incremented on each save, and `save`
+    /// only writes when the stored version is still the one the record was
+    /// loaded at, or last saved at. Otherwise `SurrealdbError::VersionConflict`
+    /// is returned, e.g. when another request saved the session in the
+    /// meantime, on this replica or another one. Through the `SessionStore`
+    /// trait it is a `Backend` error; `is_version_conflict` recognizes it.
+    /// Run `create_data_model` again after enabling this.
+    ///
+    /// The loaded version travels with the record, in its data under
+    /// `SESSION_VERSION_KEY`, and is not stored with the session. Records
+    /// that don't carry it, e.g. built by hand, are saved without a check,
+    /// and saves by stores without this enabled reset the version.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// let my_surreal_store = my_surreal_store.with_versioned_saves(true);
+    /// my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_versioned_saves(mut self, versioned_saves: bool) -> Self {
+        self.config.versioned_saves = versioned_saves;
+        self
+    }
+
+    /// Makes `create_data_model` define an `updated_at` field that
+    /// SurrealDB sets to the current time on every write of a session.
+    /// Needed by `load_with_updated_at` and `save_if_unmodified_since`. Run
+    /// `create_data_model` again after enabling this.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// let my_surreal_store = my_surreal_store.with_timestamps(true);
+    /// my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_timestamps(mut self, timestamps: bool) -> Self {
+        self.config.timestamps = timestamps;
+        self
+    }
+
+    /// Makes `load` record when a session was last read in a
+    /// `last_accessed` field, updated by the same query that reads the
+    /// session. Sessions that were never loaded hold NONE. Run
+    /// `create_data_model` again after enabling this. With timestamps
+    /// enabled every load also moves `updated_at`, since it is a write.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// let my_surreal_store = my_surreal_store.with_track_access(true);
+    /// my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_track_access(mut self, track_access: bool) -> Self {
+        self.config.track_access = track_access;
+        self
+    }
+
+    /// Sets the largest encoded session, in bytes, that `create` and `save`
+    /// send to the database; None disables the check. Larger sessions fail
+    /// with `SurrealdbError::SessionTooLarge` naming the session and its
+    /// size instead of an opaque transport error. Defaults to 16 MiB.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// let my_surreal_store = my_surreal_store.with_max_payload_bytes(Some(1 << 20));
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_max_payload_bytes(mut self, max_payload_bytes: Option<usize>) -> Self {
+        self.config.max_payload_bytes = max_payload_bytes;
+        self
+    }
+
+    /// Makes `create` idempotent within `window`: a session with the same
+    /// data and expiry date as one created less than `window` ago gets that
+    /// session's ID instead of a new row, so retried requests don't race
+    /// two cookies. Sessions of different clients that happen to be
+    /// identical are merged as well, so keep the window short. Run
+    /// `create_data_model` again after enabling this.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// let my_surreal_store = my_surreal_store
+    ///     .with_idempotent_create(Some(std::time::Duration::from_secs(5)));
+    /// my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_idempotent_create(mut self, window: Option<StdDuration>) -> Self {
+        self.config.idempotent_create_window = window;
+        self
+    }
+
+    /// Sets the clock used for expiry decisions made on the client side,
+    /// e.g. by `with_reject_expired_on_create`. Queries keep comparing
+    /// against SurrealDB's `time::now()` unless `with_bind_now` is enabled.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// let my_surreal_store = my_surreal_store.with_clock(SystemClock);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_clock<C>(mut self, clock: C) -> Self
+    where
+        C: Clock + 'static
+    {
+        self.clock = ConfiguredClock(Arc::new(clock));
+        self
+    }
+
+    /// When enabled, every query deciding whether a session expired binds
+    /// the time of the store's clock as `$now` instead of using SurrealDB's
+    /// `time::now()`, so that a `ManualClock` freezes time on both sides.
+    /// Leave it off in production: the clocks of many application servers
+    /// drift apart while the database has only one.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// let clock = ManualClock::new(tower_sessions::cookie::time::OffsetDateTime::now_utc());
+    /// let my_surreal_store = my_surreal_store
+    ///     .with_clock(clock)
+    ///     .with_bind_now(true);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_bind_now(mut self, bind_now: bool) -> Self {
+        self.config.bind_now = bind_now;
+        self
+    }
+
+    /// Value bound as `$now`. Queries use `$now ?? time::now()`, so None
+    /// leaves the decision to the database clock.
+    fn bound_now(&self) -> Result<Option<Datetime>, SurrealdbError> {
+        match self.config.bind_now {
+            true => Ok(Some(to_surreal_datetime(self.clock.0.now())?))
+            , false => Ok(None)
+        }
+    }
+
+    fn session_versions(&self) -> std::sync::MutexGuard<'_, HashMap<String, TrackedVersion>> {
+        self.session_versions.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Marks `record` as loaded at `version`, under a new load token.
+    fn stamp_version(&self, record: &mut Record, version: u64) {
+        if !self.config.versioned_saves {
+            return
+        }
+        let loaded = LoadedVersion {
+            version
+            , load: uuid::Uuid::new_v4().to_string()
+        };
+        if let Ok(value) = serde_json::to_value(loaded) {
+            record.data.insert(SESSION_VERSION_KEY.to_string(), value);
+        }
+    }
+
+    fn loaded_version(record: &Record) -> Option<LoadedVersion> {
+        record.data.get(SESSION_VERSION_KEY)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+    }
+
+    /// Version a save of `record` expects to find: the one its load last
+    /// saved, or else the one it was loaded at. None for records that were
+    /// never loaded with versioned saves enabled.
+    fn expected_version(&self, record: &Record) -> Option<u64> {
+        let loaded = Self::loaded_version(record)?;
+        let saved = self.session_versions().get(&loaded.load).map(|tracked| tracked.version);
+        Some(saved.unwrap_or(loaded.version))
+    }
+
+    /// Remembers that the load of `record` saved it at `version`, so that
+    /// saving the same record again doesn't conflict with itself.
+    fn track_version(&self, record: &Record, version: u64) {
+        let Some(loaded) = Self::loaded_version(record) else {
+            return
+        };
+        self.session_versions().insert(loaded.load, TrackedVersion {
+            id: record.id
+            , version
+            , expiry_date: record.expiry_date
+        });
+    }
+
+    /// Forgets the saved versions of the given sessions once they are gone.
+    fn forget_versions(&self, session_ids: &[Id]) {
+        if self.config.versioned_saves {
+            self.session_versions().retain(|_, tracked| !session_ids.contains(&tracked.id));
+        }
+    }
+
+    /// Forgets the versions of sessions expiring before `cutoff`, which are
+    /// gone or about to be, so that the map doesn't keep growing.
+    fn prune_versions_expiring_before(&self, cutoff: OffsetDateTime) {
+        if self.config.versioned_saves {
+            self.session_versions().retain(|_, tracked| tracked.expiry_date >= cutoff);
+        }
+    }
+
+    /// Starts the version of a session that was just created.
+    async fn start_version(&self, record: &mut Record) -> session_store::Result<()> {
+        if !self.config.versioned_saves {
+            return Ok(())
+        }
+        self.client.query("UPDATE type::thing($sessions_table, $id) SET version = 1;")
+            .bind(("sessions_table", self.config.sessions_table.clone()))
+            .bind(("id", record.id.0.to_string()))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .check()
+            .map_err(SurrealdbError::QueryFailed)?;
+        self.stamp_version(record, 1);
+        Ok(())
+    }
+
+    /// Returns the ID of a live session created within `window` with the
+    /// same data and expiry date as `record`. Matching fingerprints are
+    /// compared in full as the fingerprint is only a checksum.
+    async fn recent_identical_create(
+        &self
+        , record: &Record
+        , fingerprint: i64
+        , window: StdDuration
+    ) -> session_store::Result<Option<Id>> {
+        let candidates: Vec<IdentifiedDatabaseRecord> = self.client.query(r#"
+                select
+                    id
+                    , record
+                from type::table($table)
+                where
+                    create_fingerprint = $fingerprint
+                    and created_at > ($now ?? time::now()) - <duration> $window
+                    and expiry_date > ($now ?? time::now())
+            "#).bind(("table", self.config.sessions_table.clone()))
+            .bind(("now", self.bound_now()?))
+            .bind(("fingerprint", fingerprint))
+            .bind(("window", format!("{}ms", window.as_millis())))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .take(0)
+            .map_err(SurrealdbError::QueryFailed)?;
+        let expiry_date = truncate_expiry_date(record.expiry_date)?;
+        for candidate in candidates {
+            let Ok(existing) = decode_record(&candidate.record) else {
+                continue
+            };
+            if existing.data == record.data && truncate_expiry_date(existing.expiry_date)? == expiry_date {
+                return Ok(Some(candidate.id.id.try_into()?))
+            }
+        }
+        Ok(None)
+    }
+
+    /// Records the fingerprint of a new session when idempotent creates
+    /// are enabled.
+    async fn store_create_fingerprint(
+        &self
+        , session_id: &Id
+        , fingerprint: Option<i64>
+    ) -> session_store::Result<()> {
+        let Some(fingerprint) = fingerprint else {
+            return Ok(())
+        };
+        self.client.query(r#"
+                UPDATE type::thing($sessions_table, $id) SET
+                    create_fingerprint = $fingerprint
+                    , created_at = $now ?? time::now();
+            "#).bind(("sessions_table", self.config.sessions_table.clone()))
+            .bind(("now", self.bound_now()?))
+            .bind(("id", session_id.0.to_string()))
+            .bind(("fingerprint", fingerprint))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .check()
+            .map_err(SurrealdbError::QueryFailed)?;
+        Ok(())
+    }
+
+    /// Sends a transaction taking the next ID from the counter for a new
+    /// session until it commits, and returns the record ID of the session.
+    /// `id_statement` is the index of the `CREATE` statement. Transactions
+    /// that lost a conflict with a concurrent create are sent again, and an
+    /// ID that is already taken moves the counter on, e.g. after a backup
+    /// restore left it behind the table contents.
+    async fn create_with_counter<Q>(
+        &self
+        , send: impl Fn() -> Q
+        , id_statement: usize
+    ) -> session_store::Result<RecordId>
+    where
+        Q: IntoFuture<Output = surrealdb::Result<Response>>
+    {
+        let mut attempt = 1;
+        let mut conflicts = 0;
+        while attempt <= CREATE_ID_CONFLICT_ATTEMPTS {
+            let mut response_result = send().await;
+            if response_result.is_err() {
+                for _ in 0..4 {
+                    response_result = send().await;
+                    if response_result.is_ok() { break }
+                }
+            }
+            let mut response = response_result
+                .map_err(SurrealdbError::QueryFailed)?;
+            let id_option: Option<RecordId> = match response.take((id_statement, "id")) {
+                Ok(id_option) => id_option
+                , Err(e) if e.to_string().contains(RETRYABLE_CONFLICT) => {
+                    // a concurrent create incremented the counter first
+                    conflicts += 1;
+                    if conflicts == COUNTER_CONFLICT_ATTEMPTS {
+                        return Err(SurrealdbError::TransactionConflict.into())
+                    }
+                    tokio::time::sleep(StdDuration::from_millis(u64::from(conflicts % 10))).await;
+                    continue
+                }
+                , Err(e) if e.to_string().contains("already exists") => {
+                    // the failed transaction rolled back the increment, so
+                    // move the counter past the taken ID
+                    debug!(
+                        "Session ID taken on create attempt {} of {}: {}"
+                        , attempt
+                        , CREATE_ID_CONFLICT_ATTEMPTS
+                        , e
+                    );
+                    self.client.query(r#"UPSERT type::thing($counter_table, "counter") SET num += 1;"#)
+                        .bind(("counter_table", self.config.sessions_latest_id_table.clone()))
+                        .await
+                        .map_err(SurrealdbError::QueryFailed)?
+                        .check()
+                        .map_err(SurrealdbError::QueryFailed)?;
+                    attempt += 1;
+                    continue
+                }
+                , Err(e) => return Err(self.counter_out_of_range(&mut response)
+                    .unwrap_or(SurrealdbError::QueryFailed(e))
+                    .into())
+            };
+            return Ok(id_option.ok_or(SurrealdbError::Unknown(
+                "Record was not created so no ID was returned".into()
+            ))?)
+        }
+        Err(SurrealdbError::Unknown(format!(
+            "Could not create session: every ID tried in {CREATE_ID_CONFLICT_ATTEMPTS} attempts \
+            was already taken. The counter in {} is behind the contents of {}."
+            , self.config.sessions_latest_id_table
+            , self.config.sessions_table
+        )).into())
+    }
+
+    /// Returns `SurrealdbError::CounterOutOfRange` when the guard in
+    /// `NEXT_COUNTER_VALUE` failed. The errors are taken out of `response`,
+    /// so only call this once the query is known to have failed.
+    fn counter_out_of_range(&self, response: &mut Response) -> Option<SurrealdbError> {
+        self.counter_error(&response.take_errors())
+    }
+
+    fn counter_error(&self, errors: &HashMap<usize, surrealdb::Error>) -> Option<SurrealdbError> {
+        errors.values()
+            .find(|e| e.to_string().contains(COUNTER_OUT_OF_RANGE))
+            .map(|e| SurrealdbError::CounterOutOfRange {
+                counter_table: self.config.sessions_latest_id_table.clone()
+                , reason: e.to_string()
+            })
+    }
+
+    /// `save` for versioned mode. The version check and the write happen
+    /// in a single statement.
+    async fn save_versioned(&self, record: &Record) -> session_store::Result<()> {
+        let surrealdb_record: DatabaseRecord = record.try_into()?;
+        self.check_payload_size(record, &surrealdb_record.record)?;
+        let expected = self.expected_version(record);
+        let updated: Vec<u64> = self.client.query(r#"
+                UPDATE $ids SET
+                    expiry_date = <datetime> $expiry_date
+                    , record = $record
+                    , user_key = $user_key
+                    , version = (version ?? 0) + 1
+                WHERE $expected = NONE OR (version ?? 0) = $expected
+                RETURN VALUE version;
+            "#).bind(("ids", self.record_ids(&record.id)))
+            .bind(("expiry_date", format_expiry_date(record.expiry_date)?))
+            .bind(("record", ByteBuf::from(surrealdb_record.record.clone())))
+            .bind(("user_key", self.extract_user_key(record)))
+            .bind(("expected", expected))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .check()
+            .map_err(SurrealdbError::QueryFailed)?
+            .take(0)
+            .map_err(SurrealdbError::QueryFailed)?;
+        if let Some(version) = updated.first() {
+            self.track_version(record, *version);
+            return Ok(())
+        }
+        let found: Vec<u64> = self.client.query("select value (version ?? 0) from $ids")
+            .bind(("ids", self.record_ids(&record.id)))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .take(0)
+            .map_err(SurrealdbError::QueryFailed)?;
+        if let (Some(found), Some(expected)) = (found.first(), expected) {
+            return Err(SurrealdbError::VersionConflict {
+                id: record.id
+                , expected
+                , found: *found
+            }.into())
+        }
+        if self.config.strict_save {
+            return Err(SurrealdbError::RecordNotFound(record.id).into())
+        }
+        self.client.query(r#"
+                UPSERT type::thing($sessions_table, $id) SET
+                    expiry_date = <datetime> $expiry_date
+                    , record = $record
+                    , user_key = $user_key
+                    , version = 1;
+            "#).bind(("sessions_table", self.config.sessions_table.clone()))
+            .bind(("id", record.id.0.to_string()))
+            .bind(("expiry_date", format_expiry_date(record.expiry_date)?))
+            .bind(("record", ByteBuf::from(surrealdb_record.record)))
+            .bind(("user_key", self.extract_user_key(record)))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .check()
+            .map_err(SurrealdbError::QueryFailed)?;
+        self.track_version(record, 1);
+        Ok(())
+    }
+
+    /// Refuses to take an ID from the counter for a record user, who may
+    /// not write the counter table, see `create_data_model`.
+    fn check_counter_allowed(&self) -> Result<(), SurrealdbError> {
+        match &self.config.record_access {
+            Some(record_access) => Err(SurrealdbError::IdGeneratorRequired {
+                record_access: record_access.clone()
+            })
+            , None => Ok(())
+        }
+    }
+
+    fn check_payload_size(&self, record: &Record, encoded: &[u8]) -> session_store::Result<()> {
+        match self.config.max_payload_bytes {
+            Some(limit) if encoded.len() > limit => Err(SurrealdbError::SessionTooLarge {
+                id: record.id
+                , size: encoded.len()
+                , limit
+            }.into())
+            , _ => Ok(())
+        }
+    }
+
+    /// Moves the expiry date of a session created already expired to the
+    /// default expiry, when one is configured.
+    fn apply_default_expiry(&self, record: &mut Record) {
+        let Some(default_expiry) = self.config.default_expiry else {
+            return
+        };
+        let now = self.clock.0.now();
+        if record.expiry_date > now {
+            return
+        }
+        let expiry_date = now.saturating_add(
+            Duration::try_from(default_expiry).unwrap_or(Duration::MAX)
+        );
+        warn!(
+            "Session {} was created with the past expiry date {}, using {} instead"
+            , record.id
+            , record.expiry_date
+            , expiry_date
+        );
+        record.expiry_date = expiry_date;
+    }
+
+    fn check_not_expired(&self, record: &Record) -> session_store::Result<()> {
+        if !self.config.reject_expired_on_create {
+            return Ok(())
+        }
+        let now = self.clock.0.now();
+        if record.expiry_date <= now {
+            return Err(SurrealdbError::SessionAlreadyExpired {
+                id: record.id
+                , expiry_date: record.expiry_date
+                , now
+            }.into())
+        }
+        Ok(())
+    }
+
+    /// Moves the expiry date of a live session to `new_expiry` without
+    /// sending or rewriting its data, e.g. for sliding expiration where
+    /// `save` would otherwise re-encode the whole session on every request.
+    /// Returns whether a session was touched; expired sessions are not
+    /// brought back.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::{SessionStore, cookie::time::{Duration, OffsetDateTime}, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let mut record = Record { id: Id::default(), data: Default::default(), expiry_date: OffsetDateTime::now_utc() + Duration::hours(1) };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// # let session_id = record.id;
+    /// let touched = my_surreal_store
+    ///     .touch(&session_id, OffsetDateTime::now_utc() + Duration::hours(2))
+    ///     .await?;
+    /// # assert!(touched);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn touch(&self, session_id: &Id, new_expiry: OffsetDateTime) -> session_store::Result<bool> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let touched: Vec<SurrealRecordId> = self.client.query(r#"
+                UPDATE $ids SET expiry_date = <datetime> $expiry_date
+                WHERE expiry_date > ($now ?? time::now())
+                RETURN VALUE id;
+            "#).bind(("ids", self.record_ids(session_id)))
+            .bind(("now", self.bound_now()?))
+            .bind(("expiry_date", format_expiry_date(new_expiry)?))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .check()
+            .map_err(SurrealdbError::QueryFailed)?
+            .take(0)
+            .map_err(SurrealdbError::QueryFailed)?;
+        for tracked in self.session_versions().values_mut() {
+            if tracked.id == *session_id {
+                tracked.expiry_date = new_expiry;
+            }
+        }
+        Ok(!touched.is_empty())
+    }
+
+    /// Sets a single key of a live session's data without the caller
+    /// loading and saving the whole session. The session is only written
+    /// back when nobody changed it since it was read, otherwise it is read
+    /// again, so concurrent patches of different keys all land. Returns
+    /// whether a live session was patched. Patches leave the version of
+    /// `with_versioned_saves` alone, so a request that loaded the session
+    /// before patching it can still save it, replacing the whole data.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::{SessionStore, cookie::time::{Duration, OffsetDateTime}, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let mut record = Record { id: Id::default(), data: Default::default(), expiry_date: OffsetDateTime::now_utc() + Duration::hours(1) };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// # let session_id = record.id;
+    /// let patched = my_surreal_store
+    ///     .patch_data(&session_id, "csrf_rotated", serde_json::json!(true))
+    ///     .await?;
+    /// # assert!(patched);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn patch_data(
+        &self
+        , session_id: &Id
+        , key: &str
+        , value: serde_json::Value
+    ) -> session_store::Result<bool> {
+        self.modify_data(session_id, |data| {
+            data.insert(key.to_string(), value.clone());
+        }).await
+    }
+
+    /// Removes a single key from a live session's data, the counterpart of
+    /// `patch_data`. Returns whether a live session was found, whether or
+    /// not it held the key.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::{SessionStore, cookie::time::{Duration, OffsetDateTime}, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let mut record = Record { id: Id::default(), data: Default::default(), expiry_date: OffsetDateTime::now_utc() + Duration::hours(1) };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// # let session_id = record.id;
+    /// let patched = my_surreal_store
+    ///     .remove_data_key(&session_id, "csrf_rotated")
+    ///     .await?;
+    /// # assert!(patched);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn remove_data_key(&self, session_id: &Id, key: &str) -> session_store::Result<bool> {
+        self.modify_data(session_id, |data| {
+            data.remove(key);
+        }).await
+    }
+
+    /// Applies `modify` to the data of a live session. The write only goes
+    /// through when the stored bytes are still the ones that were read.
+    async fn modify_data<F>(&self, session_id: &Id, modify: F) -> session_store::Result<bool>
+    where
+        F: Fn(&mut HashMap<String, serde_json::Value>)
+    {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        for _ in 0..PATCH_CONFLICT_ATTEMPTS {
+            let current: Option<PatchedDatabaseRecord> = self.client.query(r#"
+                    select
+                        id
+                        , record
+                    from $ids
+                    where
+                        expiry_date > ($now ?? time::now())
+                "#).bind(("ids", self.record_ids(session_id)))
+                .bind(("now", self.bound_now()?))
+                .await
+                .map_err(SurrealdbError::QueryFailed)?
+                .take(0)
+                .map_err(SurrealdbError::QueryFailed)?;
+            let Some(current) = current else {
+                return Ok(false)
+            };
+            let mut record = decode_record(&current.record)
+                .map_err(|e| SurrealdbError::SessionDecodeFailed {
+                    id: *session_id
+                    , length: current.record.len()
+                    , source: Box::new(e)
+                })?;
+            record.id = *session_id;
+            modify(&mut record.data);
+            let encoded = encode_record(&record)?;
+            self.check_payload_size(&record, &encoded)?;
+            let updated: Vec<SurrealRecordId> = self.client.query(r#"
+                    UPDATE $id SET
+                        record = $record
+                        , user_key = $user_key
+                    WHERE record = $read_record AND expiry_date > ($now ?? time::now())
+                    RETURN VALUE id;
+                "#).bind(("id", current.id))
+                .bind(("now", self.bound_now()?))
+                .bind(("record", ByteBuf::from(encoded)))
+                .bind(("user_key", self.extract_user_key(&record)))
+                .bind(("read_record", ByteBuf::from(current.record)))
+                .await
+                .map_err(SurrealdbError::QueryFailed)?
+                .check()
+                .map_err(SurrealdbError::QueryFailed)?
+                .take(0)
+                .map_err(SurrealdbError::QueryFailed)?;
+            if !updated.is_empty() {
+                return Ok(true)
+            }
+        }
+        Err(SurrealdbError::TransactionConflict.into())
+    }
+
+    /// Stores a new session like `create` and relates it to `link_id`
+    /// through the `link_table` edge, e.g. `session->signed_up_as->user:1`,
+    /// in the same transaction. When the edge can't be written, e.g.
+    /// because the schema of `link_table` rejects the target, no session is
+    /// stored either. The ID comes from the ID generator when one is set
+    /// and from the counter otherwise; the ID of `record` is always
+    /// replaced.
+    /// ```
+    /// # use surrealdb::{Surreal, RecordId, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::{SessionStore, cookie::time::{Duration, OffsetDateTime}, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let mut record = Record {
+    ///     id: Id::default()
+    ///     , data: Default::default()
+    ///     , expiry_date: OffsetDateTime::now_utc() + Duration::hours(1)
+    /// };
+    /// my_surreal_store
+    ///     .create_linked(&mut record, "signed_up_as", RecordId::from_table_key("user", "alice"))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn create_linked(
+        &self
+        , record: &mut Record
+        , link_table: &str
+        , link_id: impl Into<SurrealRecordId>
+    ) -> session_store::Result<()> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        self.apply_default_expiry(record);
+        self.check_not_expired(record)?;
+        let surrealdb_record: DatabaseRecord = (&*record).try_into()?;
+        self.check_payload_size(record, &surrealdb_record.record)?;
+        let fingerprint = match self.config.idempotent_create_window {
+            Some(_) => Some(create_fingerprint(record)?)
+            , None => None
+        };
+        let id_statement = match self.id_generator {
+            Some(_) => "LET $num = $generated_id;".to_string()
+            , None => {
+                self.check_counter_allowed()?;
+                format!("LET $num = {NEXT_COUNTER_VALUE};")
+            }
+        };
+        let query = format!(r#"
+            BEGIN TRANSACTION;
+            {id_statement}
+            LET $new_session = type::thing($sessions_table, <string> $num);
+            CREATE $new_session SET
+                expiry_date = <datetime> $expiry_date
+                , record = $record
+                , user_key = $user_key;
+            RELATE $new_session->{}->$link_id;
+            COMMIT TRANSACTION;"#
+            , escape_identifier(link_table)?
+        );
+        let link_id = link_id.into();
+        let expiry_date = format_expiry_date(record.expiry_date)?;
+        let user_key = self.extract_user_key(record);
+        let send = |generated_id: Option<String>| self.client.query(query.as_str())
+            .bind(("counter_table", self.config.sessions_latest_id_table.clone()))
+            .bind(("sessions_table", self.config.sessions_table.clone()))
+            .bind(("generated_id", generated_id))
+            .bind(("expiry_date", expiry_date.clone()))
+            .bind(("record", ByteBuf::from(surrealdb_record.record.clone())))
+            .bind(("user_key", user_key.clone()))
+            .bind(("link_id", link_id.clone()));
+        // statements 0 and 1 are the LETs
+        let session_id = match &self.id_generator {
+            Some(generator) => {
+                let mut created = None;
+                for attempt in 1..=CREATE_ID_CONFLICT_ATTEMPTS {
+                    // the generated bits are kept as they are, like in `create`
+                    let generated_id = (generator.0.next_id().await? as i128).to_string();
+                    let mut response = send(Some(generated_id)).await
+                        .map_err(SurrealdbError::QueryFailed)?;
+                    match response.take::<Option<RecordId>>((2, "id")) {
+                        Ok(session_id) => {
+                            created = Some(session_id.ok_or(SurrealdbError::Unknown(
+                                "Linked session was not created so no ID was returned".into()
+                            ))?);
+                            break
+                        }
+                        , Err(e) if e.to_string().contains("already exists") => debug!(
+                            "Generated session ID taken on linked create attempt {} of {}: {}"
+                            , attempt
+                            , CREATE_ID_CONFLICT_ATTEMPTS
+                            , e
+                        )
+                        , Err(e) => return Err(SurrealdbError::QueryFailed(e).into())
+                    }
+                }
+                created.ok_or(SurrealdbError::Unknown(format!(
+                    "Could not create linked session: every ID generated in \
+                    {CREATE_ID_CONFLICT_ATTEMPTS} attempts was already taken"
+                )))?
+            }
+            , None => self.create_with_counter(|| send(None), 2).await?
+        };
+        record.id = session_id.id.try_into()?;
+        self.start_version(record).await?;
+        self.store_create_fingerprint(&record.id, fingerprint).await?;
+        debug!("Created session {} linked through {}", record.id, link_table);
+        Ok(())
+    }
+
+    /// Deletes a session like `delete` and reports whether it existed, e.g.
+    /// to tell a logout apart from a session that was already gone. Missing
+    /// sessions are not an error.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::{SessionStore, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let expiry_date = tower_sessions::cookie::time::OffsetDateTime::now_utc() + tower_sessions::cookie::time::Duration::hours(1);
+    /// # let mut record = Record { id: Id::default(), data: Default::default(), expiry_date };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// # let session_id = record.id;
+    /// if !my_surreal_store.delete_returning(&session_id).await? {
+    ///     // the session was already gone
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn delete_returning(&self, session_id: &Id) -> session_store::Result<bool> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let deleted: Vec<SurrealRecordId> = self.client.query("delete $ids return before")
+            .bind(("ids", self.record_ids(session_id)))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .check()
+            .map_err(SurrealdbError::QueryFailed)?
+            .take((0, "id"))
+            .map_err(SurrealdbError::QueryFailed)?;
+        self.forget_versions(&[*session_id]);
+        Ok(!deleted.is_empty())
+    }
+
+    /// Deletes a session like `delete` and returns the record that was
+    /// removed, or None when there was no such session. A removed record
+    /// that can't be decoded is handled like in `load`, as set with
+    /// `with_on_decode_error`; the session is deleted either way.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::{SessionStore, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let expiry_date = tower_sessions::cookie::time::OffsetDateTime::now_utc() + tower_sessions::cookie::time::Duration::hours(1);
+    /// # let mut record = Record { id: Id::default(), data: Default::default(), expiry_date };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// # let session_id = record.id;
+    /// let removed = my_surreal_store.delete_returning_record(&session_id).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn delete_returning_record(
+        &self
+        , session_id: &Id
+    ) -> session_store::Result<Option<Record>> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let deleted: Vec<DatabaseRecord> = self.client.query("delete $ids return before")
+            .bind(("ids", self.record_ids(session_id)))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .check()
+            .map_err(SurrealdbError::QueryFailed)?
+            .take(0)
+            .map_err(SurrealdbError::QueryFailed)?;
+        self.forget_versions(&[*session_id]);
+        let Some(database_record) = deleted.into_iter().next() else {
+            return Ok(None)
+        };
+        match decode_record(&database_record.record) {
+            Ok(mut record) => {
+                record.id = *session_id;
+                Ok(Some(record))
+            }
+            , Err(e) => {
+                // the row is gone already, only the record is lost
+                self.skip_undecodable(session_id, database_record.record.len(), e, false).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Deletes the listed sessions, e.g. to revoke every device of a
+    /// deactivated user, and returns how many of them existed. Long lists
+    /// are split into statements of 1000 sessions, all run in one
+    /// transaction, so either every listed session is removed or none is.
+    /// Missing and duplicate IDs are ignored.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::{SessionStore, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let expiry_date = tower_sessions::cookie::time::OffsetDateTime::now_utc() + tower_sessions::cookie::time::Duration::hours(1);
+    /// # let mut record = Record { id: Id::default(), data: Default::default(), expiry_date };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// let deleted = my_surreal_store.delete_many(&[record.id, Id(-1)]).await?;
+    /// assert_eq!(deleted, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn delete_many(&self, session_ids: &[Id]) -> session_store::Result<u64> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let unique_ids: Vec<Id> = session_ids.iter()
+            .copied()
+            .collect::<HashSet<Id>>()
+            .into_iter()
+            .collect();
+        if unique_ids.is_empty() {
+            return Ok(0)
+        }
+        // every chunk is bound before anything runs, so a bad ID fails the
+        // whole call instead of leaving it half applied
+        let chunks: Vec<Vec<SurrealRecordId>> = unique_ids.chunks(DELETE_MANY_CHUNK_SIZE)
+            .map(|chunk| chunk.iter()
+                .flat_map(|session_id| self.record_ids(session_id))
+                .collect()
+            )
+            .collect();
+        let mut query = String::from("BEGIN TRANSACTION;\n");
+        for index in 0..chunks.len() {
+            query.push_str(&format!("delete $ids_{index} return before;\n"));
+        }
+        query.push_str("COMMIT TRANSACTION;");
+        let mut request = self.client.query(query);
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            request = request.bind((format!("ids_{index}"), chunk));
+        }
+        let mut result_obj = request.await
+            .map_err(SurrealdbError::QueryFailed)?
+            .check()
+            .map_err(SurrealdbError::QueryFailed)?;
+        // a session stored under both its current and its legacy key counts once
+        let mut deleted = HashSet::new();
+        for index in 0..result_obj.num_statements() {
+            let record_ids: Vec<RecordId> = result_obj
+                .take((index, "id"))
+                .map_err(SurrealdbError::QueryFailed)?;
+            for record_id in record_ids {
+                deleted.insert(Id::try_from(record_id.id)?);
+            }
+        }
+        self.forget_versions(&unique_ids);
+        Ok(deleted.len() as u64)
+    }
+
+    /// Removes expired sessions like `delete_expired` and returns how many
+    /// were removed, e.g. to report it as a metric. Expired sessions are
+    /// archived first when archive mode is enabled. The count is also
+    /// logged, at info level when sessions were removed and at debug
+    /// level otherwise.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let purged = my_surreal_store.delete_expired_counted().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn delete_expired_counted(&self) -> session_store::Result<u64> {
+        let deleted = self.purge_expired().await?;
+        match deleted {
+            0 => debug!("No expired sessions to delete from {}", self.config.sessions_table)
+            , deleted => info!(
+                "Deleted {} expired sessions from {}"
+                , deleted
+                , self.config.sessions_table
+            )
+        }
+        Ok(deleted)
+    }
+
+    /// Query removing every expired session, archiving it first when
+    /// archive mode is enabled, and the index of the statement returning
+    /// the removed rows.
+    fn expired_deletion_query(&self) -> Result<(String, usize), SurrealdbError> {
+        let sessions_table = escape_identifier(&self.config.sessions_table)?;
+        match &self.config.archive_table {
+            // statement 0 is the LET and statement 1 the INSERT
+            Some(archive_table) => Ok((format!(r#"
+                    BEGIN TRANSACTION;
+                    LET $now = $now ?? time::now();
+                    INSERT INTO {1} (
+                        select
+                            record::id(id) as id
+                            , expiry_date
+                            , record
+                            , user_key
+                            , $now as archived_at
+                        from {0}
+                        where expiry_date <= $now
+                    );
+                    delete {0}
+                    where expiry_date <= $now
+                    return before;
+                    COMMIT TRANSACTION;
+                "#, sessions_table
+                , escape_identifier(archive_table)?
+            ), 2))
+            , None => Ok((format!(r#"
+                    delete {sessions_table}
+                    where expiry_date <= ($now ?? time::now())
+                    return before
+                "#
+            ), 0))
+        }
+    }
+
+    async fn purge_expired(&self) -> session_store::Result<u64> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let (query, delete_statement) = self.expired_deletion_query()?;
+        let deleted: Vec<SurrealRecordId> = self.client.query(query)
+            .bind(("now", self.bound_now()?))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .check()
+            .map_err(SurrealdbError::QueryFailed)?
+            .take((delete_statement, "id"))
+            .map_err(SurrealdbError::QueryFailed)?;
+        self.prune_versions_expiring_before(self.clock.0.now());
+        Ok(deleted.len() as u64)
+    }
+
+    /// Removes every expired session like `delete_expired` and returns the
+    /// removed sessions, e.g. to audit log who was logged out. The sessions
+    /// are selected and removed by one statement, so every returned session
+    /// is gone and no removed session is missing from the result. They are
+    /// archived first when archive mode is enabled. Sessions that can't be
+    /// decoded are removed all the same and handled like `load` handles
+    /// them, per `on_decode_error`.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// for record in my_surreal_store.drain_expired().await? {
+    ///     println!("Session {} expired at {}", record.id, record.expiry_date);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn drain_expired(&self) -> session_store::Result<Vec<Record>> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let (query, delete_statement) = self.expired_deletion_query()?;
+        let deleted: Vec<IdentifiedDatabaseRecord> = self.client.query(query)
+            .bind(("now", self.bound_now()?))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .check()
+            .map_err(SurrealdbError::QueryFailed)?
+            .take(delete_statement)
+            .map_err(SurrealdbError::QueryFailed)?;
+        self.prune_versions_expiring_before(self.clock.0.now());
+        let mut records = Vec::with_capacity(deleted.len());
+        for stored in deleted {
+            let session_id: Id = stored.id.id.try_into()?;
+            match decode_record(&stored.record) {
+                Ok(mut record) => {
+                    record.id = session_id;
+                    records.push(record);
+                }
+                // already removed
+                , Err(e) => self.skip_undecodable(&session_id, stored.record.len(), e, false).await?
+            }
+        }
+        Ok(records)
+    }
+
+    /// Deletes every session with an expiry date before `cutoff` and
+    /// returns how many were removed. A cutoff in the future invalidates
+    /// sessions that would expire soon, e.g. ahead of a maintenance window;
+    /// one in the past removes already expired sessions like
+    /// `delete_expired`. Sessions are never archived by this method.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::cookie::time::{Duration, OffsetDateTime};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let removed = my_surreal_store
+    ///     .delete_sessions_expiring_before(OffsetDateTime::now_utc() + Duration::minutes(10))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn delete_sessions_expiring_before(
+        &self
+        , cutoff: OffsetDateTime
+    ) -> session_store::Result<u64> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let query = format!(r#"
+                delete {}
+                where expiry_date < <datetime> $cutoff
+                return before
+            "#, escape_identifier(&self.config.sessions_table)?
+        );
+        let deleted: Vec<SurrealRecordId> = self.client.query(query)
+            .bind(("cutoff", format_expiry_date(cutoff)?))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .check()
+            .map_err(SurrealdbError::QueryFailed)?
+            .take((0, "id"))
+            .map_err(SurrealdbError::QueryFailed)?;
+        self.prune_versions_expiring_before(cutoff);
+        Ok(deleted.len() as u64)
+    }
+
+    /// Removes expired sessions in batches of at most `batch_size` rows,
+    /// sleeping for `pause` between batches, so that very large tables are
+    /// cleaned up without one long running transaction. Stops once a batch
+    /// removes fewer than `batch_size` rows and returns the total removed.
+    /// Expired sessions are archived first when archive mode is enabled. A
+    /// `batch_size` of 0 is treated as 1.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let purged = my_surreal_store
+    ///     .delete_expired_batched(10_000, std::time::Duration::from_millis(100))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn delete_expired_batched(
+        &self
+        , batch_size: usize
+        , pause: StdDuration
+    ) -> session_store::Result<u64> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let batch_size = batch_size.max(1);
+        let sessions_table = escape_identifier(&self.config.sessions_table)?;
+        let (query, delete_statement) = match &self.config.archive_table {
+            Some(archive_table) => (format!(r#"
+                    BEGIN TRANSACTION;
+                    LET $now = $now ?? time::now();
+                    LET $ids = (
+                        select value id from {0}
+                        where expiry_date <= $now
+                        limit $batch_size
+                    );
+                    INSERT INTO {1} (
+                        select
+                            record::id(id) as id
+                            , expiry_date
+                            , record
+                            , user_key
+                            , $now as archived_at
+                        from $ids
+                    );
+                    delete $ids return before;
+                    COMMIT TRANSACTION;
+                "#, sessions_table
+                , escape_identifier(archive_table)?
+            ), 3)
+            , None => (format!(r#"
+                    BEGIN TRANSACTION;
+                    LET $ids = (
+                        select value id from {sessions_table}
+                        where expiry_date <= ($now ?? time::now())
+                        limit $batch_size
+                    );
+                    delete $ids return before;
+                    COMMIT TRANSACTION;
+                "#
+            ), 1)
+        };
+        let mut total = 0;
+        loop {
+            let deleted: Vec<SurrealRecordId> = self.client.query(query.as_str())
+                .bind(("now", self.bound_now()?))
+                .bind(("batch_size", batch_size))
+                .await
+                .map_err(SurrealdbError::QueryFailed)?
+                .check()
+                .map_err(SurrealdbError::QueryFailed)?
+                .take((delete_statement, "id"))
+                .map_err(SurrealdbError::QueryFailed)?;
+            total += deleted.len() as u64;
+            if deleted.len() < batch_size {
+                self.prune_versions_expiring_before(self.clock.0.now());
+                return Ok(total)
+            }
+            tokio::time::sleep(pause).await;
+        }
+    }
+
+    /// **Logs everyone out.** Removes every session, live or expired, in
+    /// batches so that large tables don't need one huge transaction, and
+    /// returns how many were removed. Archived sessions are kept. With
+    /// `reset_counter` the ID counter is then moved back like
+    /// `reset_counter` does, i.e. to 0 unless sessions were created while
+    /// the table was being cleared. This is deliberately not reachable
+    /// through `SessionStore`.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let removed = my_surreal_store.delete_all(true).await?;
+    /// # assert_eq!(removed, 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn delete_all(&self, reset_counter: bool) -> session_store::Result<u64> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let query = format!(r#"
+                BEGIN TRANSACTION;
+                LET $ids = (select value id from {} limit $batch_size);
+                delete $ids return before;
+                COMMIT TRANSACTION;
+            "#, escape_identifier(&self.config.sessions_table)?
+        );
+        let mut total = 0;
+        loop {
+            let deleted: Vec<SurrealRecordId> = self.client.query(query.as_str())
+                .bind(("batch_size", DELETE_ALL_BATCH_SIZE))
+                .await
+                .map_err(SurrealdbError::QueryFailed)?
+                .check()
+                .map_err(SurrealdbError::QueryFailed)?
+                .take((1, "id"))
+                .map_err(SurrealdbError::QueryFailed)?;
+            total += deleted.len() as u64;
+            if deleted.len() < DELETE_ALL_BATCH_SIZE {
+                break
+            }
+        }
+        self.session_versions().clear();
+        warn!("Removed all {} sessions from {}", total, self.config.sessions_table);
+        if reset_counter {
+            self.reset_counter().await
+                .map_err(|e| SurrealdbError::Unknown(format!(
+                    "Removed {total} sessions but could not reset the ID counter: {e:#}"
+                )))?;
+        }
+        Ok(total)
+    }
+
+    /// Permanently removes archived sessions that were archived more than
+    /// `older_than` ago. Does nothing when archive mode is not enabled.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::cookie::time::{Duration, OffsetDateTime};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// my_surreal_store.purge_archive(Duration::days(30)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn purge_archive(&self, older_than: Duration) -> session_store::Result<()> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let Some(archive_table) = &self.config.archive_table else {
+            return Ok(())
+        };
+        let cutoff = format_expiry_date(self.clock.0.now().saturating_sub(older_than))?;
+        let query = format!(r#"
+                delete {}
+                where archived_at < <datetime> $cutoff
+            "#, escape_identifier(archive_table)?
+        );
+        self.client.query(query)
+            .bind(("cutoff", cutoff))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .check()
+            .map_err(SurrealdbError::QueryFailed)?;
+        Ok(())
+    }
+
+    /// Reads back the metadata record written by `create_data_model`.
+    /// Returns None when the data model was never created by a version
+    /// of the store that writes it.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let metadata = my_surreal_store.stored_metadata().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn stored_metadata(&self) -> anyhow::Result<Option<StoreMetadata>> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let mut response = self.client.query(r#"
+                select
+                    schema_version
+                    , crate_version
+                    , <string> created_at as created_at
+                    , <string> updated_at as updated_at
+                from type::thing($table, "meta")
+            "#).bind(("table", self.config.sessions_latest_id_table.clone()))
+            .await?
+            .check()?;
+        let database_metadata: Option<DatabaseStoreMetadata> = response.take(0)?;
+        database_metadata.map(StoreMetadata::try_from).transpose()
+    }
+
+    /// Returns the data model version that `create_data_model` recorded
+    /// in the `version` record of the `{sessions_table}_schema_meta`
+    /// table, to be compared with `CURRENT_SCHEMA_VERSION`. Returns None
+    /// when the data model was created by a version of the store that
+    /// didn't record it. Once recorded, the version is never overwritten.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let version = my_surreal_store.get_data_model_version().await?;
+    /// assert_eq!(version, Some(CURRENT_SCHEMA_VERSION));
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn get_data_model_version(&self) -> anyhow::Result<Option<u32>> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let mut response = self.client.query(r#"
+                select value version from type::thing($table, "version")
+            "#).bind(("table", self.schema_meta_table()))
+            .await?
+            .check()?;
+        let versions: Vec<u32> = response.take(0)?;
+        Ok(versions.into_iter().next())
+    }
+
+    fn schema_meta_table(&self) -> String {
+        format!("{}_schema_meta", self.config.sessions_table)
+    }
+
+    /// Compares the metadata stored in the database with what this
+    /// version of the store understands and logs a warning when the
+    /// database holds a newer schema version.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// my_surreal_store.verify_data_model().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn verify_data_model(&self) -> anyhow::Result<()> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        match self.stored_metadata().await? {
+            Some(metadata) if metadata.schema_version > SCHEMA_VERSION => warn!(
+                "Database holds schema version {} written by crate version {} but this \
+                version of the store only understands up to schema version {}"
+                , metadata.schema_version
+                , metadata.crate_version
+                , SCHEMA_VERSION
+            )
+            , Some(_) => {}
+            , None => warn!("No store metadata found. Was create_data_model run?")
+        }
+        if let Some(version) = self.get_data_model_version().await?.filter(|v| *v > CURRENT_SCHEMA_VERSION) {
+            warn!(
+                "Database holds data model version {version} but this version of the store \
+                only understands up to data model version {CURRENT_SCHEMA_VERSION}"
+            )
+        }
+        Ok(())
+    }
+
+    /// Checks that SurrealDB can be reached and accepts queries from the
+    /// signed in user by running `RETURN 1`, without touching any table.
+    /// Handy for container health probes, e.g. right after
+    /// `new_from_nothing`.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// my_surreal_store.ping().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn ping(&self) -> anyhow::Result<()> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let mut response = self.client.query("RETURN 1;").await
+            .context("Could not reach SurrealDB")?
+            .check()
+            .context("SurrealDB rejected the ping. Check the credentials and permissions")?;
+        let answer: Option<i64> = response.take(0)?;
+        if answer != Some(1) {
+            anyhow::bail!("SurrealDB answered the ping with {answer:?} instead of 1")
+        }
+        Ok(())
+    }
+
+    /// Runs `ping` and then checks that the data model is in place: the
+    /// sessions table and its fields exist and the database doesn't hold
+    /// a schema version newer than this store understands.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// my_surreal_store.ping_with_schema_check().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn ping_with_schema_check(&self) -> anyhow::Result<()> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        self.ping().await?;
+        let (table_exists, fields, _) = self.data_model_info().await?;
+        if !table_exists {
+            anyhow::bail!(
+                "Sessions table {} does not exist. Run create_data_model first"
+                , self.config.sessions_table
+            )
+        }
+        for field in ["expiry_date", "record", "user_key"] {
+            if !fields.contains_key(field) {
+                anyhow::bail!(
+                    "Field {field} of sessions table {} is not defined. Run create_data_model again"
+                    , self.config.sessions_table
+                )
+            }
+        }
+        if let Some(version) = self.get_data_model_version().await?.filter(|v| *v > CURRENT_SCHEMA_VERSION) {
+            anyhow::bail!(
+                "Database holds data model version {version} but this version of the store \
+                only understands up to data model version {CURRENT_SCHEMA_VERSION}"
+            )
+        }
+        match self.stored_metadata().await? {
+            Some(metadata) if metadata.schema_version > SCHEMA_VERSION => anyhow::bail!(
+                "Database holds schema version {} written by crate version {} but this \
+                version of the store only understands up to schema version {}"
+                , metadata.schema_version
+                , metadata.crate_version
+                , SCHEMA_VERSION
+            )
+            , _ => Ok(())
+        }
+    }
+
+    /// Checks that the fields of the sessions table still have the types
+    /// `create_data_model` gives them, e.g. after someone altered the
+    /// schema by hand or a migration went wrong. All mismatches, including
+    /// missing fields, are listed in the returned error.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// my_surreal_store.assert_schema_types().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn assert_schema_types(&self) -> anyhow::Result<()> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let (table_exists, fields, _) = self.data_model_info().await?;
+        if !table_exists {
+            anyhow::bail!(
+                "Sessions table {} does not exist. Run create_data_model first"
+                , self.config.sessions_table
+            )
+        }
+        let mut expected_types = vec![
+            ("id", "int | string")
+            , ("expiry_date", "datetime")
+            , ("record", "bytes")
+            , ("user_key", "option<string>")
+        ];
+        if self.config.versioned_saves {
+            expected_types.push(("version", "option<int>"));
+        }
+        if self.config.timestamps {
+            expected_types.push(("updated_at", "option<datetime>"));
+        }
+        if self.config.track_access {
+            expected_types.push(("last_accessed", "option<datetime>"));
+        }
+        let discrepancies: Vec<String> = expected_types.into_iter()
+            .filter_map(|(field, expected)| {
+                let actual = fields.get(field).map(|definition| field_type(definition));
+                match actual {
+                    None => Some(format!("{field} is not defined, expected {expected}"))
+                    , Some(Some(actual)) if actual == expected => None
+                    , Some(Some(actual)) => Some(format!("{field} is {actual}, expected {expected}"))
+                    , Some(None) => Some(format!("{field} has no type, expected {expected}"))
+                }
+            })
+            .collect();
+        if !discrepancies.is_empty() {
+            anyhow::bail!(
+                "Fields of sessions table {} don't have the expected types: {}"
+                , self.config.sessions_table
+                , discrepancies.join("; ")
+            )
+        }
+        Ok(())
+    }
+
+    /// Smoke test for server startup: creates a short lived session holding
+    /// a `__test__` key, loads it, saves a change, loads it again and
+    /// deletes it. The error of a failed step is a `TestConnectionError`
+    /// naming the step; the session is still deleted when possible.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// my_surreal_store.test_connection().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn test_connection(&self) -> anyhow::Result<()> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let failed = |step, reason: String| TestConnectionError { step, reason };
+        let mut record = Record {
+            id: Id::default()
+            , data: HashMap::from([("__test__".to_string(), serde_json::json!(0))])
+            , expiry_date: self.clock.0.now() + CONNECTION_TEST_TTL
+        };
+        self.create(&mut record).await
+            .map_err(|e| failed(ConnectionTestStep::Create, e.to_string()))?;
+        let checks = async {
+            let loaded = self.load(&record.id).await
+                .map_err(|e| failed(ConnectionTestStep::Load, e.to_string()))?;
+            if loaded.as_ref() != Some(&record) {
+                return Err(failed(
+                    ConnectionTestStep::Load
+                    , format!("loaded {loaded:?} instead of the created session")
+                ))
+            }
+            record.data.insert("__test__".to_string(), serde_json::json!(1));
+            self.save(&record).await
+                .map_err(|e| failed(ConnectionTestStep::Save, e.to_string()))?;
+            let reloaded = self.load(&record.id).await
+                .map_err(|e| failed(ConnectionTestStep::Reload, e.to_string()))?;
+            if reloaded.as_ref() != Some(&record) {
+                return Err(failed(
+                    ConnectionTestStep::Reload
+                    , format!("loaded {reloaded:?} instead of the saved session")
+                ))
+            }
+            Ok(())
+        }.await;
+        let deleted = self.delete(&record.id).await
+            .map_err(|e| failed(ConnectionTestStep::Delete, e.to_string()));
+        checks?;
+        deleted?;
+        Ok(())
+    }
+
+    /// Returns whether the sessions table exists along with its defined
+    /// fields and indexes.
+    async fn data_model_info(
+        &self
+    ) -> anyhow::Result<(bool, HashMap<String, String>, HashMap<String, String>)> {
+        let mut response = self.client.query("INFO FOR DB;")
+            .await?
+            .check()?;
+        let database_info: Option<DatabaseInfo> = response.take(0)?;
+        let table_exists = database_info.unwrap_or_default()
+            .tables
+            .contains_key(&self.config.sessions_table);
+        if !table_exists {
+            return Ok((false, HashMap::new(), HashMap::new()))
+        }
+        let mut response = self.client
+            .query(format!("INFO FOR TABLE {};", escape_identifier(&self.config.sessions_table)?))
+            .await?
+            .check()?;
+        let table_info: TableInfo = response.take::<Option<TableInfo>>(0)?
+            .unwrap_or_default();
+        Ok((true, table_info.fields, table_info.indexes))
+    }
+
+    /// Defines the configured namespace and database if they don't exist
+    /// yet and then selects them on the client. Defining a namespace
+    /// requires root authentication.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::{SurrealdbStore, SurrealdbStoreConfig};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// let my_surreal_store = SurrealdbStore::from_config(
+    ///     SurrealdbStoreConfig {
+    ///         namespace: Some("namespace".into())
+    ///         , database: Some("database".into())
+    ///         , ..SurrealdbStoreConfig::default()
+    ///     }
+    ///     , my_surreal
+    /// );
+    /// my_surreal_store.ensure_namespace_and_database().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn ensure_namespace_and_database(&self) -> anyhow::Result<()> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let namespace = self.config.namespace.as_ref()
+            .context("No namespace was configured for the store")?;
+        let database = self.config.database.as_ref()
+            .context("No database was configured for the store")?;
+        let definition_query = format!(r"
+                DEFINE NAMESPACE IF NOT EXISTS {0};
+                USE NS {0};
+                DEFINE DATABASE IF NOT EXISTS {1};
+            ", escape_identifier(namespace)?
+            , escape_identifier(database)?
+        );
+        let definition_result = self.client.query(definition_query)
+            .await
+            .and_then(|response| response.check());
+        if let Err(e) = definition_result {
+            let message = e.to_string();
+            if message.contains("Not enough permissions") || message.contains("IAM") {
+                return Err(anyhow::anyhow!(e).context(format!("Defining a namespace or database \
+                    requires root authentication. Sign in as a root user or create them manually.\n\
+                    Namespace was {namespace}.\n\
+                    Database was {database}"
+                )))
+            }
+            return Err(anyhow::anyhow!(e).context("Could not define namespace or database"))
+        }
+        self.client.use_ns(namespace).use_db(database).await
+            .context(format!("Could not select the namespace and database.\n\
+                Namespace was {namespace}.\n\
+                Database was {database}"
+            ))?;
+        Ok(())
+    }
+
+    /// Checks whether a live (non expired) session with the given ID
+    /// exists. Only the record ids are selected, so the record bytes are
+    /// neither fetched nor deserialized, which makes this suitable for
+    /// hot paths like rate limiters that only need to gate access.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::{SessionStore, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let expiry_date = tower_sessions::cookie::time::OffsetDateTime::now_utc() + tower_sessions::cookie::time::Duration::hours(1);
+    /// # let mut record = Record { id: Id::default(), data: Default::default(), expiry_date };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// # let session_id = record.id;
+    /// let exists = my_surreal_store.exists(&session_id).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn exists(&self, session_id: &Id) -> session_store::Result<bool> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let mut result_obj = self.client.query(r#"
+            select
+                value id
+            from $ids
+            where
+                expiry_date > ($now ?? time::now())
+            "#).bind(("ids", self.record_ids(session_id)))
+            .bind(("now", self.bound_now()?))
+            .await.map_err(SurrealdbError::QueryFailed)?;
+        let ids: Vec<SurrealRecordId> = result_obj
+            .take(0)
+            .map_err(SurrealdbError::QueryFailed)?;
+        Ok(!ids.is_empty())
+    }
+
+    /// Loads several sessions with one query, e.g. to validate a batch of
+    /// reconnecting clients, and returns the live ones by ID. Missing and
+    /// expired sessions are left out, as are duplicates in `session_ids`.
+    /// Sessions that can't be decoded are handled per `on_decode_error`,
+    /// except that they never fail the whole batch: with
+    /// `DecodeErrorBehavior::Error` the failure is logged and the session
+    /// left out.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::{SessionStore, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let expiry_date = tower_sessions::cookie::time::OffsetDateTime::now_utc() + tower_sessions::cookie::time::Duration::hours(1);
+    /// # let mut record = Record { id: Id::default(), data: Default::default(), expiry_date };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// let sessions = my_surreal_store.load_many(&[record.id, Id(-1)]).await?;
+    /// assert_eq!(sessions.len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn load_many(&self, session_ids: &[Id]) -> session_store::Result<HashMap<Id, Record>> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let unique_ids: HashSet<Id> = session_ids.iter().copied().collect();
+        if unique_ids.is_empty() {
+            return Ok(HashMap::new())
+        }
+        let record_ids: Vec<SurrealRecordId> = unique_ids.iter()
+            .flat_map(|session_id| self.record_ids(session_id))
+            .collect();
+        let access_statement = match self.config.track_access {
+            true => "UPDATE $ids SET last_accessed = $now ?? time::now() WHERE expiry_date > ($now ?? time::now()) RETURN NONE;"
+            , false => ""
+        };
+        let mut result_obj = self.client.query(format!(r#"
+            select
+                id
+                , record
+                , <string> expiry_date as expiry_date
+                , version
+            from $ids
+            where
+                expiry_date > ($now ?? time::now())
+            order by
+                expiry_date asc;
+            {access_statement}
+            "#)).bind(("ids", record_ids))
+            .bind(("now", self.bound_now()?))
+            .await.map_err(SurrealdbError::QueryFailed)?;
+        let rows: Vec<LoadedDatabaseRecord> = result_obj
+            .take(0)
+            .map_err(SurrealdbError::QueryFailed)?;
+        let mut sessions = HashMap::with_capacity(rows.len());
+        // rows expiring later come later, so like `load` they win over
+        // duplicates stored under a legacy key
+        for data in rows {
+            let Some(record_id) = data.id else {
+                continue
+            };
+            let session_id: Id = record_id.id.try_into()?;
+            match decode_record(&data.record) {
+                Ok(mut record) => {
+                    record.id = session_id;
+                    apply_stored_expiry_date(&mut record, &data.expiry_date)?;
+                    self.stamp_version(&mut record, data.version.unwrap_or(0));
+                    sessions.insert(session_id, record);
+                }
+                , Err(e) => {
+                    sessions.remove(&session_id);
+                    if let Err(e) = self.skip_undecodable(&session_id, data.record.len(), e, true).await {
+                        error!("Leaving session {} out of the batch: {}", session_id, e);
+                    }
+                }
+            }
+        }
+        Ok(sessions)
+    }
+
+    /// Returns the number of live sessions, expiring after the current
+    /// instant exactly like `load` decides it, including the store's clock
+    /// when `with_bind_now` is enabled. Rows are counted in the database
+    /// rather than fetched.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let signed_in = my_surreal_store.count().await?;
+    /// # assert_eq!(signed_in, 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn count(&self) -> session_store::Result<u64> {
+        self.count_by_expiry(false).await
+    }
+
+    /// Returns the number of expired sessions still waiting for
+    /// `delete_expired`, e.g. to alert when cleanup falls behind. It uses
+    /// the same boundary as `count`, so the two add up to every stored
+    /// session.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let backlog = my_surreal_store.count_expired().await?;
+    /// # assert_eq!(backlog, 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn count_expired(&self) -> session_store::Result<u64> {
+        self.count_by_expiry(true).await
+    }
+
+    async fn count_by_expiry(&self, expired: bool) -> session_store::Result<u64> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        // one expression for both sides, so every session is on exactly one
+        let count: Option<u64> = self.client.query(r#"
+            select
+                count() as count
+            from type::table($table)
+            where
+                (expiry_date <= ($now ?? time::now())) = $expired
+            group all
+            "#).bind(("table", self.config.sessions_table.clone()))
+            .bind(("now", self.bound_now()?))
+            .bind(("expired", expired))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .take((0, "count"))
+            .map_err(SurrealdbError::QueryFailed)?;
+        Ok(count.unwrap_or(0))
+    }
+
+    /// Returns the IDs of sessions stored in more than one row, i.e. under
+    /// both their string key and the numeric key older versions wrote,
+    /// which can happen after a partial restore. `load` uses the row that
+    /// expires last; the others can be removed by the operator.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let duplicated_ids = my_surreal_store.find_duplicates().await?;
+    /// # assert!(duplicated_ids.is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn find_duplicates(&self) -> session_store::Result<Vec<Id>> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let query = format!(r#"
+                select
+                    value id
+                from {}
+                where
+                    type::is::number(record::id(id))
+                    and array::len((
+                        select value id from type::thing($sessions_table, <string> record::id($parent.id))
+                    )) > 0
+            "#, escape_identifier(&self.config.sessions_table)?
+        );
+        let legacy_ids: Vec<RecordId> = self.client.query(query)
+            .bind(("sessions_table", self.config.sessions_table.clone()))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .check()
+            .map_err(SurrealdbError::QueryFailed)?
+            .take(0)
+            .map_err(SurrealdbError::QueryFailed)?;
+        legacy_ids.into_iter()
+            .map(|record_id| Id::try_from(record_id.id).map_err(Into::into))
+            .collect()
+    }
+
+    /// Same as `exists`, kept for existing callers.
+    #[deprecated(note = "use `exists` instead")]
+    pub async fn session_exists(&self, session_id: &Id) -> session_store::Result<bool> {
+        self.exists(session_id).await
+    }
+
+    /// Returns only the metadata (expiry date) of a live session. The
+    /// `record` column is neither fetched nor deserialized which makes
+    /// this a lot cheaper than `load` for large sessions.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::{SessionStore, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let expiry_date = tower_sessions::cookie::time::OffsetDateTime::now_utc() + tower_sessions::cookie::time::Duration::hours(1);
+    /// # let mut record = Record { id: Id::default(), data: Default::default(), expiry_date };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// # let session_id = record.id;
+    /// let metadata = my_surreal_store.get_session_metadata_only(&session_id).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn get_session_metadata_only(
+        &self
+        , session_id: &Id
+    ) -> session_store::Result<Option<SessionMetadata>> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let mut result_obj = self.client.query(r#"
+            select
+                <string> expiry_date as expiry_date
+            from $ids
+            where
+                expiry_date > ($now ?? time::now())
+            "#).bind(("ids", self.record_ids(session_id)))
+            .bind(("now", self.bound_now()?))
+            .await.map_err(SurrealdbError::QueryFailed)?;
+        let result: Option<DatabaseMetadata> = result_obj
+            .take(0)
+            .map_err(SurrealdbError::QueryFailed)?;
+        result.map(|metadata| Ok(SessionMetadata {
+                expiry_date: OffsetDateTime::parse(&metadata.expiry_date, &Rfc3339)
+                    .map_err(|e| SurrealdbError::DeserializationFailed(e.into()))?
+                , id: *session_id
+            }))
+            .transpose()
+    }
+
+    /// Lists one page of live sessions, ordered by expiry date and then by
+    /// ID, without reading their data. Each page is read by one query, so
+    /// it is consistent in itself. Pages are addressed by offset though:
+    /// sessions created, deleted or expiring between two calls shift the
+    /// following pages, so a session may show up twice or be skipped when
+    /// the table changes while it is walked.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let page = my_surreal_store.list_ids(Pagination {
+    ///     limit: 50
+    ///     , offset: 1000
+    ///     , order: SortOrder::ExpiryAscending
+    /// }).await?;
+    /// for summary in page.items {
+    ///     println!("Session {} expires at {}", summary.id, summary.expiry_date);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn list_ids(&self, page: Pagination) -> session_store::Result<Page<SessionSummary>> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        if page.limit == 0 {
+            return Ok(Page {
+                items: Vec::new()
+                , next_offset: None
+            })
+        }
+        let limit = page.limit;
+        let direction = match page.order {
+            SortOrder::ExpiryAscending => "asc"
+            , SortOrder::ExpiryDescending => "desc"
+        };
+        let rows: Vec<DatabaseSessionSummary> = self.client.query(format!(r#"
+            select
+                id
+                , <string> expiry_date as expiry_date
+                , IF created_at != NONE THEN <string> created_at END as created_at
+                , IF updated_at != NONE THEN <string> updated_at END as updated_at
+            from type::table($table)
+            where
+                expiry_date > ($now ?? time::now())
+            order by
+                expiry_date {direction}
+                , id {direction}
+            limit $limit
+            start $start
+            "#)).bind(("table", self.config.sessions_table.clone()))
+            .bind(("now", self.bound_now()?))
+            .bind(("limit", limit))
+            .bind(("start", page.offset))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .take(0)
+            .map_err(SurrealdbError::QueryFailed)?;
+        let parse = |timestamp: &str| OffsetDateTime::parse(timestamp, &Rfc3339)
+            .map_err(|e| SurrealdbError::DeserializationFailed(e.into()));
+        let next_offset = (rows.len() as u64 == limit).then_some(page.offset + limit);
+        let items = rows.into_iter()
+            .map(|row| Ok(SessionSummary {
+                id: row.id.id.try_into()?
+                , expiry_date: parse(&row.expiry_date)?
+                , created_at: row.created_at.as_deref().map(parse).transpose()?
+                , updated_at: row.updated_at.as_deref().map(parse).transpose()?
+            }))
+            .collect::<Result<Vec<_>, SurrealdbError>>()?;
+        Ok(Page {
+            items
+            , next_offset
+        })
+    }
+
+    /// Loads a session whether or not it has expired, e.g. for support
+    /// engineers looking into why a user was logged out. Expired sessions
+    /// stay readable until `delete_expired` removes them. The flag is true
+    /// when the session has expired. Not used by the `SessionStore` impl.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::{SessionStore, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let expiry_date = tower_sessions::cookie::time::OffsetDateTime::now_utc() + tower_sessions::cookie::time::Duration::hours(1);
+    /// # let mut record = Record { id: Id::default(), data: Default::default(), expiry_date };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// # let session_id = record.id;
+    /// if let Some((record, expired)) = my_surreal_store.load_ignoring_expiry(&session_id).await? {
+    ///     // ...
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn load_ignoring_expiry(
+        &self
+        , session_id: &Id
+    ) -> session_store::Result<Option<(Record, bool)>> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let mut result_obj = self.client.query(r#"
+            select
+                record
+                , expiry_date <= ($now ?? time::now()) as expired
+            from $ids
+            "#).bind(("ids", self.record_ids(session_id)))
+            .bind(("now", self.bound_now()?))
+            .await.map_err(SurrealdbError::QueryFailed)?;
+        let result: Option<ExpiryCheckedDatabaseRecord> = result_obj
+            .take(0)
+            .map_err(SurrealdbError::QueryFailed)?;
+        let Some(stored) = result else {
+            return Ok(None)
+        };
+        match decode_record(&stored.record) {
+            Ok(mut record) => {
+                record.id = *session_id;
+                Ok(Some((record, stored.expired)))
+            }
+            , Err(e) => {
+                self.skip_undecodable(session_id, stored.record.len(), e, true).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Loads a live session together with the time it was last written,
+    /// to be passed to `save_if_unmodified_since`. Requires timestamps to
+    /// be enabled; sessions not written since then report the Unix epoch.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::{SessionStore, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into())
+    /// #     .with_timestamps(true);
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let expiry_date = tower_sessions::cookie::time::OffsetDateTime::now_utc() + tower_sessions::cookie::time::Duration::hours(1);
+    /// # let mut record = Record { id: Id::default(), data: Default::default(), expiry_date };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// # let session_id = record.id;
+    /// if let Some((record, updated_at)) = my_surreal_store.load_with_updated_at(&session_id).await? {
+    ///     // ...
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn load_with_updated_at(
+        &self
+        , session_id: &Id
+    ) -> session_store::Result<Option<(Record, OffsetDateTime)>> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        self.check_timestamps()?;
+        let mut result_obj = self.client.query(r#"
+            select
+                record
+                , <string> (updated_at ?? d"1970-01-01T00:00:00Z") as updated_at
+                , version
+            from $ids
+            where
+                expiry_date > ($now ?? time::now())
+            "#).bind(("ids", self.record_ids(session_id)))
+            .bind(("now", self.bound_now()?))
+            .await.map_err(SurrealdbError::QueryFailed)?;
+        let result: Option<TimestampedDatabaseRecord> = result_obj
+            .take(0)
+            .map_err(SurrealdbError::QueryFailed)?;
+        let Some(stored) = result else {
+            return Ok(None)
+        };
+        let mut record = match decode_record(&stored.record) {
+            Ok(record) => record
+            , Err(e) => {
+                self.skip_undecodable(session_id, stored.record.len(), e, true).await?;
+                return Ok(None)
+            }
+        };
+        record.id = *session_id;
+        self.stamp_version(&mut record, stored.version.unwrap_or(0));
+        let updated_at = OffsetDateTime::parse(&stored.updated_at, &Rfc3339)
+            .map_err(|e| SurrealdbError::DeserializationFailed(e.into()))?;
+        Ok(Some((record, updated_at)))
+    }
+
+    /// Saves a session only when it hasn't been written since
+    /// `seen_updated_at`, as returned by `load_with_updated_at`. The check
+    /// and the write happen in one statement on the server, so middleware
+    /// can do a read-modify-write without a version column. Returns
+    /// `SaveOutcome::Conflict` with the current timestamp when someone else
+    /// wrote the session in the meantime and `SurrealdbError::RecordNotFound`
+    /// when it no longer exists.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::{SessionStore, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into())
+    /// #     .with_timestamps(true);
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let expiry_date = tower_sessions::cookie::time::OffsetDateTime::now_utc() + tower_sessions::cookie::time::Duration::hours(1);
+    /// # let mut record = Record { id: Id::default(), data: Default::default(), expiry_date };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// # let session_id = record.id;
+    /// let (mut record, updated_at) = my_surreal_store.load_with_updated_at(&session_id).await?
+    ///     .expect("the session was just created");
+    /// record.data.insert("visits".into(), serde_json::json!(1));
+    /// match my_surreal_store.save_if_unmodified_since(&record, updated_at).await? {
+    ///     SaveOutcome::Saved => {}
+    ///     , SaveOutcome::Conflict { current_updated_at } => {
+    ///         // reload and try again
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn save_if_unmodified_since(
+        &self
+        , record: &Record
+        , seen_updated_at: OffsetDateTime
+    ) -> session_store::Result<SaveOutcome> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        self.check_timestamps()?;
+        self.check_not_expired(record)?;
+        let surrealdb_record: DatabaseRecord = record.try_into()?;
+        self.check_payload_size(record, &surrealdb_record.record)?;
+        let seen = seen_updated_at.to_offset(UtcOffset::UTC).format(&Rfc3339)
+            .map_err(|e| SurrealdbError::SerializationFailed(e.into()))?;
+        // versioned saves of other replicas must notice this write
+        let version_update = match self.config.versioned_saves {
+            true => ", version = (version ?? 0) + 1"
+            , false => ""
+        };
+        let mut response = self.client.query(format!(r#"
+                UPDATE $ids SET
+                    expiry_date = <datetime> $expiry_date
+                    , record = $record
+                    , user_key = $user_key
+                    {version_update}
+                WHERE (updated_at ?? d"1970-01-01T00:00:00Z") = <datetime> $seen
+                RETURN VALUE version ?? 0;
+                select value <string> (updated_at ?? d"1970-01-01T00:00:00Z") from $ids;
+            "#)).bind(("ids", self.record_ids(&record.id)))
+            .bind(("expiry_date", format_expiry_date(record.expiry_date)?))
+            .bind(("record", ByteBuf::from(surrealdb_record.record)))
+            .bind(("user_key", self.extract_user_key(record)))
+            .bind(("seen", seen))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .check()
+            .map_err(SurrealdbError::QueryFailed)?;
+        let updated: Vec<u64> = response.take(0)
+            .map_err(SurrealdbError::QueryFailed)?;
+        if let Some(version) = updated.first() {
+            if self.config.versioned_saves {
+                self.track_version(record, *version);
+            }
+            return Ok(SaveOutcome::Saved)
+        }
+        let current: Option<String> = response.take(1)
+            .map_err(SurrealdbError::QueryFailed)?;
+        let current = current.ok_or(SurrealdbError::RecordNotFound(record.id))?;
+        Ok(SaveOutcome::Conflict {
+            current_updated_at: OffsetDateTime::parse(&current, &Rfc3339)
+                .map_err(|e| SurrealdbError::DeserializationFailed(e.into()))?
+        })
+    }
+
+    fn check_timestamps(&self) -> session_store::Result<()> {
+        if !self.config.timestamps {
+            return Err(SurrealdbError::Unknown(
+                "Timestamps are not enabled on this store. Use with_timestamps(true) and \
+                run create_data_model again".into()
+            ).into())
+        }
+        Ok(())
+    }
+
+    /// Bulk inserts sessions, e.g. when migrating from another session
+    /// store. IDs are assigned like `create` assigns them, per
+    /// `IdAssignment` and the ID generator, and returned in the same order
+    /// as the input. Records are created in chunks of up to 1000, each in a single
+    /// transaction, so a failure leaves the chunks before it imported and
+    /// nothing after it. A record above `max_payload_bytes` fails the
+    /// import before anything is written.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::{cookie::time::{Duration, OffsetDateTime}, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let records = vec![Record {
+    ///     id: Id::default()
+    ///     , data: Default::default()
+    ///     , expiry_date: OffsetDateTime::now_utc() + Duration::hours(1)
+    /// }];
+    /// let ids = my_surreal_store.import_sessions(records).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn import_sessions(&self, records: Vec<Record>) -> session_store::Result<Vec<Id>> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        if self.id_generator.is_none() {
+            self.check_counter_allowed()?;
+        }
+        let mut encoded = Vec::with_capacity(records.len());
+        for record in &records {
+            let surrealdb_record: DatabaseRecord = record.try_into()?;
+            self.check_payload_size(record, &surrealdb_record.record)?;
+            encoded.push((record, surrealdb_record.record));
+        }
+        let mut ids = Vec::with_capacity(records.len());
+        let mut chunk = Vec::new();
+        let mut chunk_bytes = 0;
+        for (record, record_data) in encoded {
+            if !chunk.is_empty()
+                && (chunk.len() == IMPORT_CHUNK_SIZE || chunk_bytes + record_data.len() > IMPORT_CHUNK_BYTES) {
+                ids.extend(self.import_chunk(std::mem::take(&mut chunk)).await?);
+                chunk_bytes = 0;
+            }
+            chunk_bytes += record_data.len();
+            chunk.push((record, record_data));
+        }
+        if !chunk.is_empty() {
+            ids.extend(self.import_chunk(chunk).await?);
+        }
+        Ok(ids)
+    }
+
+    /// Creates the encoded sessions of one `import_sessions` chunk in a
+    /// single transaction. Like `create` it tries new generated IDs when a
+    /// generated one is taken.
+    async fn import_chunk(&self, chunk: Vec<(&Record, Vec<u8>)>) -> session_store::Result<Vec<Id>> {
+        let count = chunk.len();
+        let mut query = String::from("BEGIN TRANSACTION;");
+        let mut bindings = Vec::with_capacity(count * 3);
+        let mut record_bindings = Vec::with_capacity(count);
+        let mut taken_bindings = Vec::new();
+        for (index, (record, record_data)) in chunk.into_iter().enumerate() {
+            let new_id = match self.id_generator {
+                Some(_) => format!("$generated_id_{index}")
+                , None => NEXT_COUNTER_VALUE.to_string()
+            };
+            let id_value = match self.config.id_assignment {
+                // legacy rows keyed by a number count as taken as well
+                IdAssignment::HonorCallerThenCounter => {
+                    bindings.push((format!("caller_id_{index}"), Some(record.id.0.to_string())));
+                    taken_bindings.push((format!("taken_ids_{index}"), self.record_ids(&record.id)));
+                    format!(
+                        "IF array::len((select value id from $taken_ids_{index})) > 0 \
+                        {{ {new_id} }} ELSE {{ $caller_id_{index} }}"
+                    )
+                }
+                , IdAssignment::CounterAlways => new_id
+            };
+            query.push_str(&format!(r#"
+                LET $num = {1};
+                CREATE type::thing($sessions_table, <string> $num) SET
+                    expiry_date = <datetime> $expiry_date_{0}
+                    , record = $record_{0}
+                    , user_key = $user_key_{0};"#
+                , index
+                , id_value
+            ));
+            bindings.push((format!("expiry_date_{index}"), Some(format_expiry_date(record.expiry_date)?)));
+            record_bindings.push((format!("record_{index}"), ByteBuf::from(record_data)));
+            bindings.push((format!("user_key_{index}"), self.extract_user_key(record)));
+        }
+        query.push_str("\nCOMMIT TRANSACTION;");
+        for attempt in 1..=CREATE_ID_CONFLICT_ATTEMPTS {
+            let mut pending_query = self.client.query(query.as_str())
+                .bind(("counter_table", self.config.sessions_latest_id_table.clone()))
+                .bind(("sessions_table", self.config.sessions_table.clone()));
+            for binding in &bindings {
+                pending_query = pending_query.bind(binding.clone());
+            }
+            for binding in &record_bindings {
+                pending_query = pending_query.bind(binding.clone());
+            }
+            for binding in &taken_bindings {
+                pending_query = pending_query.bind(binding.clone());
+            }
+            if let Some(generator) = &self.id_generator {
+                for index in 0..count {
+                    // kept as they are, like the IDs generated by `create`
+                    let new_id = generator.0.next_id().await? as i128;
+                    pending_query = pending_query.bind((format!("generated_id_{index}"), new_id.to_string()));
+                }
+            }
+            let mut response = pending_query
+                .await
+                .map_err(SurrealdbError::QueryFailed)?;
+            let mut errors = response.take_errors();
+            if let Some(error) = self.counter_error(&errors) {
+                return Err(error.into())
+            }
+            if self.id_generator.is_some() && errors.values().any(|e| e.to_string().contains("already exists")) {
+                debug!(
+                    "Generated session ID taken on import attempt {} of {}"
+                    , attempt
+                    , CREATE_ID_CONFLICT_ATTEMPTS
+                );
+                continue
+            }
+            // the error of the first failed statement, as `check` would return
+            if let Some(error) = errors.keys().min().copied().and_then(|first| errors.remove(&first)) {
+                return Err(SurrealdbError::QueryFailed(error).into())
+            }
+            let mut ids = Vec::with_capacity(count);
+            for index in 0..count {
+                // every record issues a LET picking the ID followed by the CREATE
+                let id_option: Option<RecordId> = response.take((index * 2 + 1, "id"))
+                    .map_err(SurrealdbError::QueryFailed)?;
+                let new_id = id_option.ok_or(SurrealdbError::Unknown(
+                    "Record was not created so no ID was returned".into()
+                ))?;
+                ids.push(new_id.id.try_into()?);
+            }
+            return Ok(ids)
+        }
+        Err(SurrealdbError::Unknown(format!(
+            "Could not import sessions: some ID generated in each of {CREATE_ID_CONFLICT_ATTEMPTS} \
+            attempts was already taken"
+        )).into())
+    }
+
+    /// Returns every live (non expired) session, e.g. for backups or to
+    /// migrate to another store. The sessions are fetched in chunks but
+    /// are all held in memory at the end; use `export_sessions_chunked`
+    /// for large tables.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let records = my_surreal_store.export_all_sessions().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn export_all_sessions(&self) -> session_store::Result<Vec<Record>> {
+        self.export_sessions_chunked(EXPORT_CHUNK_SIZE)
+            .try_collect()
+            .await
+    }
+
+    /// Streams every live (non expired) session, fetching `chunk_size`
+    /// sessions per query so that large tables don't have to fit in memory.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use futures::TryStreamExt;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let mut sessions = std::pin::pin!(my_surreal_store.export_sessions_chunked(500));
+    /// while let Some(record) = sessions.try_next().await? {
+    ///     // ...
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn export_sessions_chunked(
+        &self
+        , chunk_size: u64
+    ) -> impl Stream<Item = session_store::Result<Record>> + '_ {
+        let chunk_size = chunk_size.max(1);
+        stream::try_unfold(Some(0u64), move |start| async move {
+            let Some(start) = start else {
+                return Ok(None)
+            };
+            let _in_flight = InFlightGuard::new(&self.in_flight);
+            let mut result_obj = self.client.query(r#"
+                select
+                    id
+                    , record
+                from type::table($table)
+                where
+                    expiry_date > ($now ?? time::now())
+                order by id
+                limit $limit
+                start $start
+                "#).bind(("table", self.config.sessions_table.clone()))
+                .bind(("now", self.bound_now()?))
+                .bind(("limit", chunk_size))
+                .bind(("start", start))
+                .await.map_err(SurrealdbError::QueryFailed)?;
+            let chunk: Vec<IdentifiedDatabaseRecord> = result_obj
+                .take(0)
+                .map_err(SurrealdbError::QueryFailed)?;
+            let next_start = (chunk.len() as u64 == chunk_size).then_some(start + chunk_size);
+            let mut records = Vec::with_capacity(chunk.len());
+            for stored in chunk {
+                let session_id: Id = stored.id.id.try_into()?;
+                match decode_record(&stored.record) {
+                    Ok(mut record) => {
+                        record.id = session_id;
+                        records.push(record);
+                    }
+                    // deleting would shift the rows of the following chunks
+                    , Err(e) => self.skip_undecodable(&session_id, stored.record.len(), e, false).await?
+                }
+            }
+            Ok(Some((records, next_start)))
+        })
+            .map_ok(|records| stream::iter(records.into_iter().map(Ok)))
+            .try_flatten()
+    }
+
+    /// Writes every live (non expired) session to `writer` as newline
+    /// delimited JSON, one object per session holding its ID, expiry date
+    /// and base64 encoded stored record. Returns the number of sessions
+    /// written. Use `restore_from_reader` to load the backup. Any
+    /// `AsyncWrite` works, e.g. a `tokio::fs::File`.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::{SessionStore, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let expiry_date = tower_sessions::cookie::time::OffsetDateTime::now_utc() + tower_sessions::cookie::time::Duration::hours(1);
+    /// # let mut record = Record { id: Id::default(), data: Default::default(), expiry_date };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// # let session_id = record.id;
+    /// let mut backup = Vec::new();
+    /// let exported = my_surreal_store.backup_to_writer(&mut backup).await?;
+    /// assert_eq!(exported, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn backup_to_writer<W>(&self, mut writer: W) -> anyhow::Result<u64>
+    where
+        W: AsyncWrite + Unpin
+    {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let mut sessions = std::pin::pin!(self.export_sessions_chunked(EXPORT_CHUNK_SIZE));
+        let mut count = 0;
+        while let Some(record) = sessions.try_next().await
+            .context("Could not read the sessions to back up")? {
+            let database_record = DatabaseRecord::try_from(&record)?;
+            let mut line = serde_json::to_vec(&BackupLine {
+                id: record.id.0.to_string()
+                , expiry_date: format_expiry_date(record.expiry_date)?
+                , record: BASE64_STANDARD_NO_PAD.encode(database_record.record)
+            })?;
+            line.push(b'\n');
+            writer.write_all(&line).await
+                .context(format!("Could not write session {} to the backup", record.id))?;
+            count += 1;
+        }
+        writer.flush().await.context("Could not flush the backup")?;
+        Ok(count)
+    }
+
+    /// Inserts the sessions of a backup written by `backup_to_writer`,
+    /// keeping their IDs. Sessions that can't be inserted, e.g. because
+    /// their ID is already taken, are logged and skipped. Returns the
+    /// number of sessions imported.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::{SessionStore, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let expiry_date = tower_sessions::cookie::time::OffsetDateTime::now_utc() + tower_sessions::cookie::time::Duration::hours(1);
+    /// # let mut record = Record { id: Id::default(), data: Default::default(), expiry_date };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// # let session_id = record.id;
+    /// # let mut backup = Vec::new();
+    /// # my_surreal_store.backup_to_writer(&mut backup).await?;
+    /// let restored_store = my_surreal_store.clone().with_sessions_table("restored_sessions".into());
+    /// restored_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let imported = restored_store.restore_from_reader(backup.as_slice()).await?;
+    /// assert_eq!(imported, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn restore_from_reader<R>(&self, reader: R) -> anyhow::Result<u64>
+    where
+        R: AsyncRead + Unpin
+    {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let mut lines = BufReader::new(reader).lines();
+        let mut line_number = 0;
+        let mut count = 0;
+        while let Some(line) = lines.next_line().await.context("Could not read the backup")? {
+            line_number += 1;
+            if line.trim().is_empty() {
+                continue
+            }
+            let backup_line: BackupLine = serde_json::from_str(&line)
+                .context(format!("Line {line_number} of the backup is not a session"))?;
+            let expiry_date = OffsetDateTime::parse(&backup_line.expiry_date, &Rfc3339)
+                .context(format!("Line {line_number} of the backup holds an invalid expiry date"))?;
+            let record_bytes = BASE64_STANDARD_NO_PAD.decode(&backup_line.record)
+                .context(format!("Line {line_number} of the backup holds invalid base64"))?;
+            let mut record = decode_record(&record_bytes)
+                .context(format!("Line {line_number} of the backup holds an invalid session"))?;
+            record.id = Id(backup_line.id.parse()
+                .context(format!("Line {line_number} of the backup holds an invalid ID"))?);
+            let created = self.client.query(r#"
+                    CREATE type::thing($sessions_table, $id) SET
+                        expiry_date = <datetime> $expiry_date
+                        , record = $record
+                        , user_key = $user_key;"#)
+                .bind(("sessions_table", self.config.sessions_table.clone()))
+                .bind(("id", backup_line.id))
+                .bind(("expiry_date", format_expiry_date(expiry_date)?))
+                .bind(("record", ByteBuf::from(record_bytes)))
+                .bind(("user_key", self.extract_user_key(&record)))
+                .await
+                .and_then(|response| response.check());
+            match created {
+                Ok(_) => count += 1
+                , Err(e) => warn!("Could not restore session {}: {}", record.id, e)
+            }
+        }
+        Ok(count)
+    }
+
+    /// Writes the sessions and counter tables, definitions and rows, to a
+    /// SurrealQL file with SurrealDB's export, e.g. before a migration.
+    /// Exports need an embedded engine or an HTTP connection; WebSocket
+    /// connections don't support them.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let directory = tempfile::tempdir()?;
+    /// my_surreal_store.snapshot_to_file(&directory.path().join("sessions.surql")).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    #[cfg(feature = "snapshot")]
+    pub async fn snapshot_to_file(&self, path: &Path) -> anyhow::Result<()> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        self.client.export(path)
+            .with_config()
+            .users(false)
+            .accesses(false)
+            .params(false)
+            .functions(false)
+            .analyzers(false)
+            .tables(vec![
+                self.config.sessions_table.clone()
+                , self.config.sessions_latest_id_table.clone()
+            ])
+            .await
+            .context(format!("Could not write the session snapshot to {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Replaces the sessions and counter tables with a snapshot written by
+    /// `snapshot_to_file`. The tables are removed first, like
+    /// `wipe_data_model` does, so sessions created after the snapshot are
+    /// lost. Removing the tables and importing the snapshot happen in one
+    /// transaction: a corrupt or truncated snapshot fails the restore and
+    /// keeps the current sessions.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let directory = tempfile::tempdir()?;
+    /// let snapshot = directory.path().join("sessions.surql");
+    /// my_surreal_store.snapshot_to_file(&snapshot).await?;
+    /// my_surreal_store.restore_from_file(&snapshot).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    #[cfg(feature = "snapshot")]
+    pub async fn restore_from_file(&self, path: &Path) -> anyhow::Result<()> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        if !path.is_file() {
+            anyhow::bail!("There is no session snapshot at {}", path.display())
+        }
+        let snapshot = tokio::fs::read_to_string(path).await
+            .context(format!("Could not read the session snapshot at {}", path.display()))?;
+        // both data sections must be there, or the snapshot was cut short
+        let complete = snapshot.contains("OPTION IMPORT;")
+            && [&self.config.sessions_table, &self.config.sessions_latest_id_table].iter()
+                .all(|table| snapshot.contains(&format!("-- TABLE DATA: {table}\n")));
+        if !complete {
+            anyhow::bail!("{} is not a complete session snapshot", path.display())
+        }
+        // strings are exported on one line, so these lines are all index
+        // definitions. Defining an index in the same transaction that
+        // rewrites its table fails to commit, so they are applied after it.
+        let (indexes, statements): (Vec<&str>, Vec<&str>) = snapshot.lines()
+            .partition(|line| line.starts_with("DEFINE INDEX "));
+        // the tables are removed and the snapshot imported in one
+        // transaction, so a corrupt snapshot leaves the current sessions be
+        self.client.query(format!(r"
+                BEGIN TRANSACTION;
+                REMOVE TABLE IF EXISTS {0};
+                REMOVE TABLE IF EXISTS {1};
+                {2}
+                ;
+                COMMIT TRANSACTION;
+            ", escape_identifier(&self.config.sessions_table)?
+            , escape_identifier(&self.config.sessions_latest_id_table)?
+            , statements.join("\n")
+            ))
+            .await?
+            .check()
+            .context(format!("Could not restore the session snapshot from {}", path.display()))?;
+        self.session_versions().clear();
+        if !indexes.is_empty() {
+            self.client.query(indexes.join("\n"))
+                .await?
+                .check()
+                .context(format!("Restored the sessions from {} but could not define their indexes", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Tries to decode every stored session, expired or not, and lists the
+    /// ones whose record is corrupted, e.g. by a partial write or a manual
+    /// edit. The table is read page by page and every failure is collected
+    /// rather than stopping at the first one.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::{SessionStore, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// for corrupted in my_surreal_store.validate_record_integrity().await? {
+    ///     my_surreal_store.delete(&corrupted.id).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn validate_record_integrity(&self) -> anyhow::Result<Vec<CorruptedSessionInfo>> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let mut corrupted = Vec::new();
+        let mut start = 0;
+        loop {
+            let page: Vec<IntegrityCheckRecord> = self.client.query(r#"
+                select
+                    id
+                    , record
+                    , <string> expiry_date as expiry_date
+                from type::table($table)
+                order by id
+                limit $limit
+                start $start
+                "#).bind(("table", self.config.sessions_table.clone()))
+                .bind(("limit", EXPORT_CHUNK_SIZE))
+                .bind(("start", start))
+                .await
+                .context(format!("Could not read sessions starting at row {start}"))?
+                .take(0)
+                .context(format!("Could not read sessions starting at row {start}"))?;
+            let page_size = page.len() as u64;
+            for stored in page {
+                let Err(e) = decode_record(&stored.record) else {
+                    continue
+                };
+                let id = match Id::try_from(stored.id.id) {
+                    Ok(id) => id
+                    , Err(id_error) => {
+                        warn!("Skipping corrupted session that has no valid ID: {}", id_error);
+                        continue
+                    }
+                };
+                corrupted.push(CorruptedSessionInfo {
+                    expiry_date: OffsetDateTime::parse(&stored.expiry_date, &Rfc3339)
+                        .context(format!("Session {id} has an invalid expiry date"))?
+                    , id
+                    , error: e.to_string()
+                });
+            }
+            if page_size < EXPORT_CHUNK_SIZE {
+                return Ok(corrupted)
+            }
+            start += EXPORT_CHUNK_SIZE;
+        }
+    }
+
+    /// Watches a session for changes using a SurrealDB live query. Every
+    /// create, update or delete of the session is pushed as a SessionEvent.
+    /// The stream ends when the live query is killed and dropping the
+    /// stream kills the live query. Live queries need a WebSocket or an
+    /// embedded connection; they are not available over HTTP.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use futures::StreamExt;
+    /// # use tower_sessions::{SessionStore, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let expiry_date = tower_sessions::cookie::time::OffsetDateTime::now_utc() + tower_sessions::cookie::time::Duration::hours(1);
+    /// # let mut record = Record { id: Id::default(), data: Default::default(), expiry_date };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// # let session_id = record.id;
+    /// let mut events = std::pin::pin!(my_surreal_store.watch_session(&session_id).await?);
+    /// # my_surreal_store.delete(&session_id).await?;
+    /// while let Some(event) = events.next().await {
+    ///     // ...
+    /// #   assert_eq!(event, SessionEvent::Deleted(session_id));
+    /// #   break;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn watch_session(
+        &self
+        , session_id: &Id
+    ) -> session_store::Result<impl Stream<Item = SessionEvent>> {
+        let mut live_queries = Vec::new();
+        for record_id in self.record_ids(session_id) {
+            let live_query = self.client
+                .select::<Option<DatabaseRecord>>(record_id)
+                .live()
+                .await
+                .map_err(SurrealdbError::QueryFailed)?;
+            live_queries.push(live_query);
+        }
+        let session_id = *session_id;
+        Ok(stream::select_all(live_queries)
+            .take_while(|notification| future::ready(notification.is_ok()))
+            .filter_map(move |notification| {
+                let event = notification.ok()
+                    .and_then(|notification| session_event(&session_id, notification));
+                future::ready(event)
+            }))
+    }
+
+    /// Waits for all in-flight session store operations, across every
+    /// clone of this store, to complete and then invalidates the SurrealDB
+    /// session. Gives up with an error after `timeout`. Clones of the
+    /// store that are still alive can start new operations, so stop
+    /// serving requests first. Streams from `watch_session` never count as
+    /// in flight and end with the session.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use tower_sessions::cookie::time::{Duration, OffsetDateTime};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// my_surreal_store.graceful_shutdown(std::time::Duration::from_secs(10)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn graceful_shutdown(self, timeout: StdDuration) -> anyhow::Result<()> {
+        let drained = tokio::time::timeout(timeout, async {
+            while self.in_flight.load(Ordering::SeqCst) > 0 {
+                tokio::time::sleep(StdDuration::from_millis(10)).await;
+            }
+        }).await;
+        if drained.is_err() {
+            anyhow::bail!(
+                "Timed out after {:?} with {} session store operations still in flight"
+                , timeout
+                , self.in_flight.load(Ordering::SeqCst)
+            )
+        }
+        self.client.invalidate().await
+            .context("Could not invalidate the SurrealDB session")?;
+        Ok(())
+    }
+
+    /// Spawns a tokio task that runs `delete_expired` every `interval`,
+    /// starting right away, and returns the store together with the task's
+    /// handle. Failed runs are logged and the task keeps going. It stops
+    /// once `true` is sent on `shutdown` or its sender is dropped, so it
+    /// can be awaited through the handle during a graceful shutdown.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let (stop_janitor, shutdown) = tokio::sync::watch::channel(false);
+    /// let (my_surreal_store, janitor) = my_surreal_store
+    ///     .into_janitor(std::time::Duration::from_secs(60), shutdown);
+    /// // serve requests with my_surreal_store, then
+    /// stop_janitor.send(true)?;
+    /// janitor.await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn into_janitor(
+        self
+        , interval: StdDuration
+        , mut shutdown: watch::Receiver<bool>
+    ) -> (SurrealdbStore<DB>, JoinHandle<()>) {
+        let janitor_store = self.clone();
+        let handle = tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                let tick = std::pin::pin!(ticker.tick());
+                // errors mean the sender is gone, which stops the janitor too
+                let stop = std::pin::pin!(shutdown.wait_for(|stop| *stop));
+                if let Either::Right(_) = future::select(tick, stop).await {
+                    break
+                }
+                if let Err(e) = janitor_store.delete_expired().await {
+                    error!("Expiry janitor could not delete expired sessions: {}", e);
+                }
+            }
+            debug!("Expiry janitor for {} stopped", janitor_store.config.sessions_table);
+        });
+        (self, handle)
+    }
+
+    /// Sets a function that extracts a user identifier from a session
+    /// record. When set, `create` and `save` also write the extracted value
+    /// into the indexed `user_key` column so sessions can be looked up by
+    /// user with `ids_for_user_key`.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let my_surreal_store = my_surreal_store.with_user_key_extractor(|record| {
+    ///     record.data.get("user_id")
+    ///         .and_then(|value| value.as_str())
+    ///         .map(String::from)
+    /// });
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_user_key_extractor<F>(mut self, extractor: F) -> Self
+    where
+        F: Fn(&Record) -> Option<String> + Send + Sync + 'static
+    {
+        self.user_key_extractor = Some(UserKeyExtractor(Arc::new(extractor)));
+        self
+    }
+
+    /// Sets the strategy `create` uses to pick the IDs of new sessions,
+    /// e.g. `UuidV4IdGenerator` for IDs that can't be guessed. Without a
+    /// generator IDs come from the counter in the sessions latest ID table,
+    /// incremented in the same transaction as the insert.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let my_surreal_store = my_surreal_store.with_id_generator(UuidV4IdGenerator);
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub fn with_id_generator<G>(mut self, generator: G) -> Self
+    where
+        G: IdGenerator + 'static
+    {
+        self.id_generator = Some(ConfiguredIdGenerator(Arc::new(generator)));
+        self
+    }
+
+    /// Record IDs under which a session may be stored: the decimal string
+    /// of the ID and, for rows written by older versions of the store, the
+    /// number itself when it fits in an i64.
+    fn record_ids(&self, session_id: &Id) -> Vec<SurrealRecordId> {
+        let mut record_ids = vec![SurrealRecordId::from_table_key(
+            &self.config.sessions_table
+            , session_id.0.to_string()
+        )];
+        if let Ok(legacy_id) = i64::try_from(session_id.0) {
+            record_ids.push(SurrealRecordId::from_table_key(&self.config.sessions_table, legacy_id));
+        }
+        record_ids
+    }
+
+    fn extract_user_key(&self, record: &Record) -> Option<String> {
+        self.user_key_extractor.as_ref().and_then(|extractor| (extractor.0)(record))
+    }
+
+    /// Deletes every live session whose data holds `value` at the dot
+    /// separated `json_path`, e.g. to log a user out on all devices after a
+    /// password change. Session data is opaque to the database, so every
+    /// session is decoded to check it; the matches are then deleted in a
+    /// single query. Returns the number of deleted sessions. When a user key
+    /// extractor is set, `ids_for_user_key` is much cheaper.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # use serde_json::json;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let removed = my_surreal_store
+    ///     .delete_all_sessions_for_data_value("user.id", &json!(42))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn delete_all_sessions_for_data_value(
+        &self
+        , json_path: &str
+        , value: &serde_json::Value
+    ) -> session_store::Result<u64> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let matching_ids: Vec<SurrealRecordId> = self.export_sessions_chunked(EXPORT_CHUNK_SIZE)
+            .try_filter(|record| future::ready(
+                value_at_path(&record.data, json_path) == Some(value)
+            ))
+            .map_ok(|record| stream::iter(self.record_ids(&record.id).into_iter().map(Ok)))
+            .try_flatten()
+            .try_collect()
+            .await?;
+        if matching_ids.is_empty() {
+            return Ok(0)
+        }
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let deleted: Vec<SurrealRecordId> = self.client.query("delete $ids return before")
+            .bind(("ids", matching_ids))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .check()
+            .map_err(SurrealdbError::QueryFailed)?
+            .take((0, "id"))
+            .map_err(SurrealdbError::QueryFailed)?;
+        Ok(deleted.len() as u64)
+    }
+
+    /// Returns the IDs of all sessions whose `user_key` column matches the
+    /// supplied key. Only sessions written while a user key extractor was
+    /// set will be found.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let ids = my_surreal_store.ids_for_user_key("user_42").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn ids_for_user_key(&self, key: &str) -> session_store::Result<Vec<Id>> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let mut result_obj = self.client.query(r#"
+            select
+                id
+            from type::table($table)
+            where
+                user_key = $user_key
+            "#).bind(("table", self.config.sessions_table.clone()))
+            .bind(("user_key", key.to_string()))
+            .await.map_err(SurrealdbError::QueryFailed)?;
+        let record_ids: Vec<RecordId> = result_obj
+            .take((0, "id"))
+            .map_err(SurrealdbError::QueryFailed)?;
+        record_ids.into_iter()
+            .map(|record_id| record_id.id.try_into().map_err(Into::into))
+            .collect()
+    }
+
+    /// Returns the live session with the lowest ID, or None when there
+    /// are no live sessions. With counter IDs this is the oldest session,
+    /// e.g. for spotting stuck sessions on a dashboard.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let oldest = my_surreal_store.get_oldest_session().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn get_oldest_session(&self) -> session_store::Result<Option<Id>> {
+        self.live_session_id_at_end("asc").await
+    }
+
+    /// Returns the live session with the highest ID, or None when there
+    /// are no live sessions. With counter IDs this is the newest session.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::*;
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let newest = my_surreal_store.get_newest_session().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn get_newest_session(&self) -> session_store::Result<Option<Id>> {
+        self.live_session_id_at_end("desc").await
+    }
+
+    async fn live_session_id_at_end(&self, direction: &str) -> session_store::Result<Option<Id>> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        // keys are decimal strings, so they are sorted as numbers rather
+        // than lexicographically
+        let mut result_obj = self.client.query(format!(r#"
+            select
+                id
+                , <number> record::id(id) as id_number
+            from type::table($table)
+            where
+                expiry_date > ($now ?? time::now())
+            order by id_number {direction}
+            limit 1
+            "#)).bind(("table", self.config.sessions_table.clone()))
+            .bind(("now", self.bound_now()?))
+            .await.map_err(SurrealdbError::QueryFailed)?;
+        let record_id: Option<RecordId> = result_obj
+            .take((0, "id"))
+            .map_err(SurrealdbError::QueryFailed)?;
+        Ok(record_id.map(|record_id| record_id.id.try_into()).transpose()?)
+    }
+}
+
+/// How `new_from_url` and `new_from_nothing_with_credentials`
+/// authenticate with SurrealDB.
+#[cfg(feature = "engine-any")]
+#[derive(Clone, PartialEq, Eq)]
+pub enum SurrealdbCredentials {
+    /// A root user
+    Root {
+        username: String,
+        password: String
+    },
+    /// A user defined on the namespace the store uses
+    Namespace {
+        username: String,
+        password: String
+    },
+    /// A user defined on the database the store uses
+    Database {
+        username: String,
+        password: String
+    },
+    /// A JWT bearer token, e.g. from Surreal Cloud or from signing in
+    /// through a record access elsewhere
+    Token {
+        token: String
+    },
+    /// Don't sign in, e.g. for embedded engines or guest access
+    None
+}
+
+#[cfg(feature = "engine-any")]
+impl Debug for SurrealdbCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SurrealdbCredentials::Root { username, .. } => f.debug_struct("Root")
+                .field("username", username)
+                .finish_non_exhaustive()
+            , SurrealdbCredentials::Namespace { username, .. } => f.debug_struct("Namespace")
+                .field("username", username)
+                .finish_non_exhaustive()
+            , SurrealdbCredentials::Database { username, .. } => f.debug_struct("Database")
+                .field("username", username)
+                .finish_non_exhaustive()
+            , SurrealdbCredentials::Token { .. } => f.debug_struct("Token")
+                .finish_non_exhaustive()
+            , SurrealdbCredentials::None => f.write_str("None")
+        }
+    }
+}
+
+/// The parts of a `new_from_url` URL. The endpoint is what the URL looks
+/// like once the namespace, database and any credentials are taken out.
+#[cfg(feature = "engine-any")]
+#[derive(Debug, PartialEq, Eq)]
+struct StoreUrl {
+    endpoint: String,
+    namespace: String,
+    database: String,
+    credentials: SurrealdbCredentials,
+    /// Whether the endpoint is a SurrealDB server rather than an embedded
+    /// engine
+    remote: bool
+}
+
+/// Schemes of the engines that talk to a SurrealDB server, which usually
+/// requires signing in.
+#[cfg(feature = "engine-any")]
+const REMOTE_SCHEMES: [&str; 4] = ["ws", "wss", "http", "https"];
+
+/// Schemes of the embedded engines. Which of them work depends on the
+/// SurrealDB features compiled in.
+#[cfg(feature = "engine-any")]
+const EMBEDDED_SCHEMES: [&str; 8] = [
+    "mem"
+    , "memory"
+    , "file"
+    , "rocksdb"
+    , "surrealkv"
+    , "tikv"
+    , "fdb"
+    , "indxdb"
+];
+
+#[cfg(feature = "engine-any")]
+fn parse_store_url(url: &str) -> anyhow::Result<StoreUrl> {
+    let mut parsed = Url::parse(url).context(format!("{url} is not a valid URL"))?;
+    let remote = REMOTE_SCHEMES.contains(&parsed.scheme());
+    if !remote && !EMBEDDED_SCHEMES.contains(&parsed.scheme()) {
+        anyhow::bail!("{url} does not start with the scheme of a SurrealDB engine, e.g. ws://")
+    }
+    if remote && parsed.host_str().is_none_or(str::is_empty) {
+        anyhow::bail!("{url} has no host to connect to")
+    }
+    let mut namespace = None;
+    let mut database = None;
+    for (key, value) in parsed.query_pairs() {
+        match key.as_ref() {
+            "ns" | "namespace" => namespace = Some(value.into_owned())
+            , "db" | "database" => database = Some(value.into_owned())
+            , _ => anyhow::bail!("Unknown URL parameter {key}. Only ns and db are understood")
+        }
+    }
+    let (Some(namespace), Some(database)) = (namespace, database) else {
+        anyhow::bail!("{url} has to name the namespace and database, e.g. ?ns=app&db=app")
+    };
+    // the URL keeps reserved characters such as @ percent-encoded
+    let decode = |part: &str| percent_decode_str(part).decode_utf8()
+        .map(String::from)
+        .context(format!("The credentials in {url} are not valid UTF-8 once decoded"));
+    let credentials = match parsed.username() {
+        "" => SurrealdbCredentials::None
+        , username => SurrealdbCredentials::Root {
+            username: decode(username)?
+            , password: decode(parsed.password().unwrap_or_default())?
+        }
+    };
+    parsed.set_query(None);
+    let endpoint = if remote {
+        // the client adds the RPC path itself
+        match parsed.port() {
+            Some(port) => format!("{}://{}:{port}", parsed.scheme(), parsed.host_str().unwrap_or_default())
+            , None => format!("{}://{}", parsed.scheme(), parsed.host_str().unwrap_or_default())
+        }
+    } else {
+        parsed.to_string()
+    };
+    Ok(StoreUrl {
+        endpoint
+        , namespace
+        , database
+        , credentials
+        , remote
+    })
+}
+
+#[cfg(feature = "engine-any")]
+async fn sign_in(
+    surreal_connection: &Surreal<Any>
+    , credentials: &SurrealdbCredentials
+    , namespace: &str
+    , database: &str
+) -> anyhow::Result<()> {
+    match credentials {
+        SurrealdbCredentials::Root { username, password } => {
+            surreal_connection.signin(Root {
+                username: username.as_str(),
+                password: password.as_str(),
+            }).await.context(format!("Root username or password was wrong.\n\
+                Username was: {username}"
+            ))?;
+        }
+        , SurrealdbCredentials::Namespace { username, password } => {
+            surreal_connection.signin(Namespace {
+                namespace,
+                username: username.as_str(),
+                password: password.as_str(),
+            }).await.context(format!("Namespace username or password was wrong.\n\
+                Namespace was: {namespace}\n\
+                Username was: {username}"
+            ))?;
+        }
+        , SurrealdbCredentials::Database { username, password } => {
+            surreal_connection.signin(Database {
+                namespace,
+                database,
+                username: username.as_str(),
+                password: password.as_str(),
+            }).await.context(format!("Database username or password was wrong.\n\
+                Namespace was: {namespace}\n\
+                Database was: {database}\n\
+                Username was: {username}"
+            ))?;
+        }
+        , SurrealdbCredentials::Token { token } => {
+            surreal_connection.authenticate(token.clone()).await
+                .context("The token was rejected. It may have expired")?;
+        }
+        , SurrealdbCredentials::None => {}
+    }
+    Ok(())
+}
+
+#[cfg(feature = "engine-any")]
+impl SurrealdbStore<Any> {
+
+    /// Enables creating a SurrealdbStore<Any> instance from nothing.
+    /// To use this please make sure you configure your env variable
     /// DB_PASSWORD to hold your password. Paswords in code is a
     /// big no no
     /// Note: Please pick appropriate values for anything else other
     /// than testing
-    /// ```ignore
+    ///
+    /// Setting `create_missing` defines the namespace and database when
+    /// they don't exist yet. This requires `username` to be a root user.
+    ///
+    /// Signing in needs a root user, which a fresh in-memory database
+    /// doesn't have, so in tests build a `SurrealdbStore<Any>` on a
+    /// `mem://` endpoint with `new` instead:
+    /// ```
+    /// use anyhow;
+    /// use surrealdb::engine::any::{self, Any};
+    /// use tower_sessions_surrealdb_store::{SchemaApplyMode, SurrealdbStore};
+    /// #[tokio::main]
+    /// async fn main() -> anyhow::Result<()>{
+    ///     let my_surreal = any::connect("mem://").await?;
+    ///     my_surreal.use_ns("namespace").use_db("database").await?;
+    ///     let my_surreal_store: SurrealdbStore<Any> = SurrealdbStore::new(
+    ///         my_surreal
+    ///         , "sessions".into()
+    ///         , "sessions_latest_id_table".into()
+    ///     );
+    ///     my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Platform-specific notes
+    ///
+    /// Against a SurrealDB server listening on `localhost:8000`:
+    /// ```no_run
     /// use anyhow;
     /// use surrealdb::engine::any::Any;
     /// use tower_sessions_surrealdb_store::SurrealdbStore;
@@ -229,11 +5453,13 @@ impl SurrealdbStore<Any> {
     ///         , "database".into()
     ///         , "sessions".into()
     ///         , "sessions_latest_id_table".into()
+    ///         , false
     ///     ).await?;
     ///     Ok(())
     /// }
     /// ```
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn new_from_nothing(
         endpoint_type: String
         , endpoint_address: String
@@ -242,41 +5468,314 @@ impl SurrealdbStore<Any> {
         , database: String
         , sessions_table: String
         , sessions_latest_id_table: String
+        , create_missing: bool
     ) -> anyhow::Result<Self> {
-        // Connect to the database
         let db_password = var("DB_PASSWORD").context("DB_PASSWORD env var not defined")?;
+        let store = Self::new_from_nothing_with_credentials(
+            format!("{endpoint_type}://{endpoint_address}")
+            , namespace
+            , database
+            , sessions_table
+            , sessions_latest_id_table
+            , SurrealdbCredentials::Root {
+                username
+                , password: db_password
+            }
+        ).await?;
+        if create_missing {
+            store.ensure_namespace_and_database().await?;
+        }
+        Ok(store)
+    }
+
+    /// Creates a SurrealdbStore<Any> on a full endpoint address such as
+    /// `ws://localhost:8000`, signing in with any kind of user. Unlike
+    /// `new_from_nothing` nothing is read from the environment. The
+    /// namespace and database have to exist; `ensure_namespace_and_database`
+    /// creates them when signed in as root.
+    ///
+    /// # Platform-specific notes
+    ///
+    /// Against a SurrealDB server listening on `localhost:8000`, with a
+    /// user defined on the database:
+    /// ```no_run
+    /// use anyhow;
+    /// use tower_sessions_surrealdb_store::{SurrealdbCredentials, SurrealdbStore};
+    /// #[tokio::main]
+    /// async fn main() -> anyhow::Result<()>{
+    ///     let my_surreal_store = SurrealdbStore::new_from_nothing_with_credentials(
+    ///         "ws://localhost:8000".into()
+    ///         , "namespace".into()
+    ///         , "database".into()
+    ///         , "sessions".into()
+    ///         , "sessions_latest_id".into()
+    ///         , SurrealdbCredentials::Database {
+    ///             username: "sessions_user".into()
+    ///             , password: std::env::var("SESSIONS_DB_PASSWORD")?
+    ///         }
+    ///     ).await?;
+    ///     Ok(())
+    /// }
+    /// ```
 
+    pub async fn new_from_nothing_with_credentials(
+        endpoint: String
+        , namespace: String
+        , database: String
+        , sessions_table: String
+        , sessions_latest_id_table: String
+        , credentials: SurrealdbCredentials
+    ) -> anyhow::Result<Self> {
         let surreal_connection: Surreal<Any> = Surreal::init();
-        surreal_connection.connect(format!("{endpoint_type}://{endpoint_address}")).await
+        surreal_connection.connect(endpoint.as_str()).await
             .context(format!("Could not connect to SurrealDB. Either the endpoint type was\
                 wrong or the endpoint address was wrong.\n\
-                Endpoint type was: {endpoint_type}\n\
-                Endpoint address was {endpoint_address}"
+                Endpoint was: {endpoint}"
             ))?;
-        
-        // Log into the database
-        surreal_connection.signin(Root {
-            username: username.as_str(),
-            password: db_password.as_str(),
-        }).await.context(format!("Username or password was wrong.\n\
-            Username was: {username}\n\
-            Can't print the password. Check it in your env var."
-        ))?;
-        
-        // Select a namespace/database
+        sign_in(&surreal_connection, &credentials, &namespace, &database).await?;
         surreal_connection.use_ns(&namespace).use_db(&database).await
             .context(format!("Check that the names or the namespace and database are correct\n\
                 that they exist.\n\
                 Namespace was {namespace}.\n\
                 Database was {database}"
             ))?;
-        Ok(
-            Self {
-                client: surreal_connection
-                , sessions_table: sessions_table
-                , sessions_latest_id_table: sessions_latest_id_table
+        Ok(Self::from_config(
+            SurrealdbStoreConfig {
+                sessions_table
+                , sessions_latest_id_table
+                , namespace: Some(namespace)
+                , database: Some(database)
+                , ..SurrealdbStoreConfig::default()
             }
-        )
+            , surreal_connection
+        ))
+    }
+
+    /// Creates a SurrealdbStore<Any> that authenticates with a JWT bearer
+    /// token instead of signing in with a username and password, as needed
+    /// for Surreal Cloud or for tokens handed out by a record access. The
+    /// endpoint is a full address such as `wss://cloud.surrealdb.com`.
+    ///
+    /// # Platform-specific notes
+    ///
+    /// A token has to be issued by the server it is used with, so there is
+    /// no in-memory variant of this example.
+    /// ```no_run
+    /// use anyhow;
+    /// use tower_sessions_surrealdb_store::SurrealdbStore;
+    /// #[tokio::main]
+    /// async fn main() -> anyhow::Result<()>{
+    ///     let my_surreal_store = SurrealdbStore::new_from_nothing_with_token(
+    ///         "wss://my-instance.aws-euw1.surreal.cloud".into()
+    ///         , std::env::var("SURREAL_TOKEN")?
+    ///         , "namespace".into()
+    ///         , "database".into()
+    ///         , "sessions".into()
+    ///         , "sessions_latest_id".into()
+    ///     ).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+
+    pub async fn new_from_nothing_with_token(
+        endpoint: String
+        , token: String
+        , namespace: String
+        , database: String
+        , sessions_table: String
+        , sessions_latest_id_table: String
+    ) -> anyhow::Result<Self> {
+        let surreal_connection: Surreal<Any> = Surreal::init();
+        surreal_connection.connect(endpoint.as_str()).await
+            .context(format!("Could not connect to SurrealDB at {endpoint}"))?;
+        surreal_connection.authenticate(token).await
+            .context("The token was rejected. It may have expired or be meant for another server")?;
+        surreal_connection.use_ns(&namespace).use_db(&database).await
+            .context(format!("Check that the names or the namespace and database are correct\n\
+                that they exist.\n\
+                Namespace was {namespace}.\n\
+                Database was {database}"
+            ))?;
+        Ok(Self::from_config(
+            SurrealdbStoreConfig {
+                sessions_table
+                , sessions_latest_id_table
+                , namespace: Some(namespace)
+                , database: Some(database)
+                , ..SurrealdbStoreConfig::default()
+            }
+            , surreal_connection
+        ))
+    }
+
+    /// Creates a SurrealdbStore<Any> from a single URL naming the engine,
+    /// the address, the namespace and the database, e.g.
+    /// `ws://localhost:8000/rpc?ns=app&db=app` or `mem://?ns=app&db=app`.
+    /// The namespace and database have to exist. Credentials passed in
+    /// take precedence over a user and password in the URL, which sign in
+    /// as root; without either nothing signs in and a warning is logged for
+    /// server engines, where that is usually a mistake.
+    /// ```
+    /// use anyhow;
+    /// use tower_sessions_surrealdb_store::{SchemaApplyMode, SurrealdbStore};
+    /// #[tokio::main]
+    /// async fn main() -> anyhow::Result<()>{
+    ///     let my_surreal_store = SurrealdbStore::new_from_url(
+    ///         "mem://?ns=namespace&db=database"
+    ///         , None
+    ///     ).await?;
+    ///     my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Platform-specific notes
+    ///
+    /// Against a SurrealDB server listening on `localhost:8000`:
+    /// ```no_run
+    /// use anyhow;
+    /// use tower_sessions_surrealdb_store::{SurrealdbCredentials, SurrealdbStore};
+    /// #[tokio::main]
+    /// async fn main() -> anyhow::Result<()>{
+    ///     let my_surreal_store = SurrealdbStore::new_from_url(
+    ///         "ws://localhost:8000/rpc?ns=namespace&db=database"
+    ///         , Some(SurrealdbCredentials::Root {
+    ///             username: "root".into()
+    ///             , password: std::env::var("DB_PASSWORD")?
+    ///         })
+    ///     ).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+
+    pub async fn new_from_url(
+        url: &str
+        , credentials: Option<SurrealdbCredentials>
+    ) -> anyhow::Result<Self> {
+        let store_url = parse_store_url(url)?;
+        let credentials = match credentials {
+            Some(credentials) => credentials
+            , None => store_url.credentials
+        };
+        let endpoint = store_url.endpoint;
+        let namespace = store_url.namespace;
+        let database = store_url.database;
+
+        let surreal_connection: Surreal<Any> = Surreal::init();
+        surreal_connection.connect(endpoint.as_str()).await
+            .context(format!("Could not connect to SurrealDB at {endpoint}"))?;
+
+        sign_in(&surreal_connection, &credentials, &namespace, &database).await?;
+        if credentials == SurrealdbCredentials::None && store_url.remote {
+            warn!("Connected to {endpoint} without signing in");
+        }
+
+        surreal_connection.use_ns(&namespace).use_db(&database).await
+            .context(format!("Check that the names or the namespace and database are correct\n\
+                that they exist.\n\
+                Namespace was {namespace}.\n\
+                Database was {database}"
+            ))?;
+        Ok(Self::from_config(
+            SurrealdbStoreConfig {
+                namespace: Some(namespace)
+                , database: Some(database)
+                , ..SurrealdbStoreConfig::default()
+            }
+            , surreal_connection
+        ))
+    }
+}
+
+#[cfg(feature = "engine-local")]
+impl SurrealdbStore<Db> {
+
+    /// Creates a store on a fresh in-process, in-memory SurrealDB. Handy
+    /// for tests and single node setups; sessions are lost when the
+    /// process exits.
+    /// ```
+    /// use surrealdb::engine::local::Db;
+    /// use tower_sessions_surrealdb_store::{SchemaApplyMode, SurrealdbStore};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let store: SurrealdbStore<Db> = SurrealdbStore::new_in_memory(
+    ///     "namespace".into()
+    ///     , "database".into()
+    ///     , "sessions".into()
+    ///     , "sessions_latest_id".into()
+    /// ).await?;
+    /// store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    pub async fn new_in_memory(
+        namespace: String
+        , database: String
+        , sessions_table: String
+        , sessions_latest_id_table: String
+    ) -> anyhow::Result<Self> {
+        let surreal_connection: Surreal<Db> = Surreal::new::<Mem>(()).await
+            .context("Could not start the in-memory SurrealDB")?;
+        surreal_connection.use_ns(&namespace).use_db(&database).await
+            .context(format!("Could not select the namespace and database.\n\
+                Namespace was {namespace}.\n\
+                Database was {database}"
+            ))?;
+        Ok(Self::from_config(
+            SurrealdbStoreConfig {
+                sessions_table
+                , sessions_latest_id_table
+                , namespace: Some(namespace)
+                , database: Some(database)
+                , ..SurrealdbStoreConfig::default()
+            }
+            , surreal_connection
+        ))
+    }
+
+    /// Creates a store on an embedded SurrealDB persisted with RocksDB at
+    /// `path`, so no separate server is needed. The namespace and database
+    /// are defined when they don't exist yet.
+    /// ```
+    /// # use tower_sessions_surrealdb_store::{SchemaApplyMode, SurrealdbStore};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let data_dir = tempfile::tempdir()?;
+    /// let store = SurrealdbStore::new_rocksdb(
+    ///     data_dir.path()
+    ///     , "namespace"
+    ///     , "database"
+    ///     , "sessions".into()
+    ///     , "sessions_latest_id".into()
+    /// ).await?;
+    /// store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+
+    #[cfg(feature = "rocksdb")]
+    pub async fn new_rocksdb(
+        path: &Path
+        , namespace: &str
+        , database: &str
+        , sessions_table: String
+        , sessions_latest_id_table: String
+    ) -> anyhow::Result<Self> {
+        let surreal_connection: Surreal<Db> = Surreal::new::<RocksDb>(path).await
+            .context(format!("Could not open the RocksDB SurrealDB at {}", path.display()))?;
+        let store = Self::from_config(
+            SurrealdbStoreConfig {
+                sessions_table
+                , sessions_latest_id_table
+                , namespace: Some(namespace.into())
+                , database: Some(database.into())
+                , ..SurrealdbStoreConfig::default()
+            }
+            , surreal_connection
+        );
+        store.ensure_namespace_and_database().await?;
+        Ok(store)
     }
 }
 
@@ -285,18 +5784,32 @@ impl<DB> ExpiredDeletion for SurrealdbStore<DB>
 where
     DB: Connection + Debug
 {
+    /// Removes every expired session, archiving it first when archive mode
+    /// is enabled. Usually run periodically with
+    /// `continuously_delete_expired`.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::{SchemaApplyMode, SurrealdbStore};
+    /// # use tower_sessions::{ExpiredDeletion, cookie::time::{Duration, OffsetDateTime}, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let mut record = Record {
+    /// #     id: Id::default()
+    /// #     , data: Default::default()
+    /// #     , expiry_date: OffsetDateTime::now_utc() - Duration::hours(1)
+    /// # };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// my_surreal_store.delete_expired().await?;
+    /// # assert_eq!(my_surreal_store.load_ignoring_expiry(&record.id).await?, None);
+    /// # Ok(())
+    /// # }
+    /// ```
     async fn delete_expired(&self) -> session_store::Result<()> {
-        let query = format!(r#"
-                delete {}
-                where expiry_date <= time::unix(time::now())
-            "#, self.sessions_table.clone()
-        );
-        self.client.query(query)
-            .await
-            .map_err(|e| Backend(e.to_string()))?
-            .check()
-            .map_err(|e| Backend(e.to_string()))?;
-        Ok(())
+        self.delete_expired_counted().await.map(|_| ())
     }
 }
 
@@ -306,90 +5819,332 @@ where
     DB: Connection + Debug
 {
 
+    /// Stores a new session. The ID of `record` is replaced by the one the
+    /// store assigns, see `IdAssignment` and `with_id_generator`.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::{SchemaApplyMode, SurrealdbStore};
+    /// # use tower_sessions::{SessionStore, cookie::time::{Duration, OffsetDateTime}, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// let mut record = Record {
+    ///     id: Id::default()
+    ///     , data: Default::default()
+    ///     , expiry_date: OffsetDateTime::now_utc() + Duration::hours(1)
+    /// };
+    /// my_surreal_store.create(&mut record).await?;
+    /// assert_eq!(my_surreal_store.load(&record.id).await?, Some(record));
+    /// # Ok(())
+    /// # }
+    /// ```
     async fn create(&self, record: &mut Record) -> session_store::Result<()> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        self.apply_default_expiry(record);
         let record_reference = &*record;
+        self.check_not_expired(record_reference)?;
         let surrealdb_record: DatabaseRecord = record_reference.try_into()?;
-        let datetime_string = record_reference.expiry_date
-            .format(&Iso8601::<{FORMAT_CONFIG}>)
-            .map_err(|e| Encode(e.to_string()))?;
-        let record_data = BASE64_STANDARD_NO_PAD.encode(surrealdb_record.record);
+        self.check_payload_size(record_reference, &surrealdb_record.record)?;
+        let fingerprint = match self.config.idempotent_create_window {
+            Some(window) => {
+                let fingerprint = create_fingerprint(record_reference)?;
+                if let Some(existing_id) = self.recent_identical_create(record_reference, fingerprint, window).await? {
+                    debug!("Identical session {} was created recently, reusing it", existing_id);
+                    record.id = existing_id;
+                    return Ok(())
+                }
+                Some(fingerprint)
+            }
+            , None => None
+        };
+        let datetime_string = format_expiry_date(record_reference.expiry_date)?;
+        let record_data = ByteBuf::from(surrealdb_record.record);
+        let user_key = self.extract_user_key(record_reference);
+        if self.config.id_assignment == IdAssignment::HonorCallerThenCounter {
+            // legacy rows keyed by a number count as taken as well
+            let honored = self.client.query(r#"
+                BEGIN TRANSACTION;
+                IF array::len((select value id from $ids)) > 0 {
+                    THROW "Session ID already exists";
+                };
+                CREATE type::thing($sessions_table, $id) SET
+                    expiry_date = <datetime> $expiry_date
+                    , record = $record
+                    , user_key = $user_key;
+                COMMIT TRANSACTION;"#)
+                .bind(("ids", self.record_ids(&record_reference.id)))
+                .bind(("sessions_table", self.config.sessions_table.clone()))
+                .bind(("id", record_reference.id.0.to_string()))
+                .bind(("expiry_date", datetime_string.clone()))
+                .bind(("record", record_data.clone()))
+                .bind(("user_key", user_key.clone()))
+                .await
+                .map_err(SurrealdbError::QueryFailed)?
+                .check();
+            match honored {
+                Ok(_) => {
+                    self.start_version(record).await?;
+                    self.store_create_fingerprint(&record.id, fingerprint).await?;
+                    debug!("Created session {}", record.id);
+                    return Ok(())
+                }
+                , Err(e) if e.to_string().contains("already exists") => debug!(
+                    "Session ID {} is taken, assigning a new one: {}"
+                    , record_reference.id
+                    , e
+                )
+                , Err(e) => return Err(SurrealdbError::QueryFailed(e).into())
+            }
+        }
+        if let Some(generator) = &self.id_generator {
+            for attempt in 1..=CREATE_ID_CONFLICT_ATTEMPTS {
+                // the generated bits are kept as they are, u128 IDs above
+                // i128::MAX simply become negative session IDs
+                let new_id = generator.0.next_id().await? as i128;
+                let mut response = self.client.query(r#"
+                    CREATE type::thing($sessions_table, $id) SET
+                        expiry_date = <datetime> $expiry_date
+                        , record = $record
+                        , user_key = $user_key;"#)
+                    .bind(("sessions_table", self.config.sessions_table.clone()))
+                    .bind(("id", new_id.to_string()))
+                    .bind(("expiry_date", datetime_string.clone()))
+                    .bind(("record", record_data.clone()))
+                    .bind(("user_key", user_key.clone()))
+                    .await
+                    .map_err(SurrealdbError::QueryFailed)?;
+                match response.take::<Option<RecordId>>((0, "id")) {
+                    Ok(_) => {
+                        record.id = Id(new_id);
+                        self.start_version(record).await?;
+                        self.store_create_fingerprint(&record.id, fingerprint).await?;
+                        debug!("Created session {}", record.id);
+                        return Ok(())
+                    }
+                    , Err(e) if e.to_string().contains("already exists") => debug!(
+                        "Generated session ID taken on create attempt {} of {}: {}"
+                        , attempt
+                        , CREATE_ID_CONFLICT_ATTEMPTS
+                        , e
+                    )
+                    , Err(e) => return Err(SurrealdbError::QueryFailed(e).into())
+                }
+            }
+            return Err(SurrealdbError::Unknown(format!(
+                "Could not create session: every ID generated in {CREATE_ID_CONFLICT_ATTEMPTS} \
+                attempts was already taken"
+            )).into())
+        }
+        self.check_counter_allowed()?;
         let query = format!(r#"
             BEGIN TRANSACTION;
-            UPSERT type::thing("{0}", "counter") SET num += 1;
-            CREATE type::thing("{1}", type::thing("{0}", "counter").num) SET
-                expiry_date = <datetime>"{2}"
-                , record = encoding::base64::decode("{3}");
-            COMMIT TRANSACTION;"#
-            , self.sessions_latest_id_table.clone()
-            , self.sessions_table.clone()
-            , datetime_string
-            , record_data
-        );
-        let mut response_result = self.client.query(query.clone()).await;
-        if response_result.is_err() {
-            for _ in 0..4 {
-                response_result = self.client.query(query.clone()).await;
-                if response_result.is_ok() { break }
-            }
-        }
-        let mut response = response_result
-            .map_err(|e| Backend(e.to_string()))?;
-        let id_option: Option<RecordId> = response.take((1, "id"))
-            .map_err(|e | Backend(e.to_string()))?;
-        let new_id = id_option.ok_or(Backend("Record was not created so no ID was returned".into()))?;
-        let SurrealId::Number(number) = new_id.id;
-        record.id.0 = number.into();
-        debug!("{:#?}\n\n", record.clone());
+            LET $num = {NEXT_COUNTER_VALUE};
+            CREATE type::thing($sessions_table, <string> $num) SET
+                expiry_date = <datetime> $expiry_date
+                , record = $record
+                , user_key = $user_key;
+            COMMIT TRANSACTION;"#);
+        // statement 0 is the LET
+        let new_id = self.create_with_counter(|| self.client.query(query.as_str())
+            .bind(("counter_table", self.config.sessions_latest_id_table.clone()))
+            .bind(("sessions_table", self.config.sessions_table.clone()))
+            .bind(("expiry_date", datetime_string.clone()))
+            .bind(("record", record_data.clone()))
+            .bind(("user_key", user_key.clone()))
+            , 1
+        ).await?;
+        record.id = new_id.id.try_into()?;
+        self.start_version(record).await?;
+        self.store_create_fingerprint(&record.id, fingerprint).await?;
+        debug!("Created session {}", record.id);
         Ok(())
     }
-    
+
+    /// Writes a session, creating it when it isn't in the store yet unless
+    /// strict save is enabled.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::{SchemaApplyMode, SurrealdbStore};
+    /// # use tower_sessions::{SessionStore, cookie::time::{Duration, OffsetDateTime}, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let mut record = Record {
+    /// #     id: Id::default()
+    /// #     , data: Default::default()
+    /// #     , expiry_date: OffsetDateTime::now_utc() + Duration::hours(1)
+    /// # };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// record.data.insert("user_id".into(), serde_json::json!(42));
+    /// my_surreal_store.save(&record).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
     async fn save(&self, record: &Record) -> session_store::Result<()> {
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        self.check_not_expired(record)?;
+        if self.config.versioned_saves {
+            return self.save_versioned(record).await
+        }
         let surrealdb_record: DatabaseRecord = record.try_into()?;
-        let id_i64: i64 = record.id.0.try_into()
-            .map_err(|_| Encode("ID was out of range for target data type of i64".into()))?;
-        let result = self.client
-            .update::<Option<DatabaseRecord>>((&self.sessions_table, id_i64))
-            .content(surrealdb_record)
-            .await;
-        result.map_err(|e| Backend(e.to_string()))?
-            .ok_or(Backend("No record was updated. Probably ID not found".into()))?;
+        self.check_payload_size(record, &surrealdb_record.record)?;
+        let expiry_date = format_expiry_date(record.expiry_date)?;
+        let record_data = ByteBuf::from(surrealdb_record.record);
+        let user_key = self.extract_user_key(record);
+        // only the columns owned by the session are set, so that fields
+        // such as last_accessed and created_at survive the save. Rows
+        // written by older versions may still be keyed by a number.
+        let updated: Vec<SurrealRecordId> = self.client.query(r#"
+                UPDATE $ids SET
+                    expiry_date = <datetime> $expiry_date
+                    , record = $record
+                    , user_key = $user_key
+                RETURN VALUE id;
+            "#).bind(("ids", self.record_ids(&record.id)))
+            .bind(("expiry_date", expiry_date.clone()))
+            .bind(("record", record_data.clone()))
+            .bind(("user_key", user_key.clone()))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .check()
+            .map_err(SurrealdbError::QueryFailed)?
+            .take(0)
+            .map_err(SurrealdbError::QueryFailed)?;
+        if !updated.is_empty() {
+            return Ok(())
+        }
+        if self.config.strict_save {
+            return Err(SurrealdbError::RecordNotFound(record.id).into())
+        }
+        // the session may have been removed by delete_expired while the
+        // client still holds a valid cookie, so write it back
+        self.client.query(r#"
+                UPSERT type::thing($sessions_table, $id) SET
+                    expiry_date = <datetime> $expiry_date
+                    , record = $record
+                    , user_key = $user_key;
+            "#).bind(("sessions_table", self.config.sessions_table.clone()))
+            .bind(("id", record.id.0.to_string()))
+            .bind(("expiry_date", expiry_date))
+            .bind(("record", record_data))
+            .bind(("user_key", user_key))
+            .await
+            .map_err(SurrealdbError::QueryFailed)?
+            .check()
+            .map_err(SurrealdbError::QueryFailed)?;
         Ok(())
     }
 
+    /// Returns a live session, or None when it doesn't exist or has
+    /// expired. What happens with a stored session that can't be decoded
+    /// is set with `with_on_decode_error`.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::{SchemaApplyMode, SurrealdbStore};
+    /// # use tower_sessions::{SessionStore, cookie::time::{Duration, OffsetDateTime}, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let mut record = Record {
+    /// #     id: Id::default()
+    /// #     , data: Default::default()
+    /// #     , expiry_date: OffsetDateTime::now_utc() + Duration::hours(1)
+    /// # };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// if let Some(record) = my_surreal_store.load(&record.id).await? {
+    ///     // ...
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
     async fn load(&self, session_id: &Id) -> session_store::Result<Option<Record>> {
-        let mut result_obj = self.client.query(r#"
+        let _in_flight = InFlightGuard::new(&self.in_flight);
+        let access_statement = match self.config.track_access {
+            true => "UPDATE $ids SET last_accessed = $now ?? time::now() WHERE expiry_date > ($now ?? time::now()) RETURN NONE;"
+            , false => ""
+        };
+        let mut result_obj = self.client.query(format!(r#"
             select
                 record
-                , expiry_date
-            from type::thing($table,$id)
+                , <string> expiry_date as expiry_date
+                , version
+            from $ids
             where
-                expiry_date > time::now()
-            "#).bind(("table", self.sessions_table.clone()))
-            .bind(("id", session_id.0))
-            .await.map_err(|e| Backend(e.to_string()))?;
-        let result: Option<DatabaseRecord> = result_obj
+                expiry_date > ($now ?? time::now())
+            order by
+                expiry_date desc;
+            {access_statement}
+            "#)).bind(("ids", self.record_ids(session_id)))
+            .bind(("now", self.bound_now()?))
+            .await.map_err(SurrealdbError::QueryFailed)?;
+        let rows: Vec<LoadedDatabaseRecord> = result_obj
             .take(0)
-            .map_err(|e| Backend(e.to_string()))?;
-        match result {
-            Some(data) => {
-                let mut prelim_record: Record = data.try_into()
-                .map_err(|_| Decode(
-                    "Database record could not be converted to type Record".into()
-                ))?;
-                prelim_record.id = session_id.clone();
+            .map_err(SurrealdbError::QueryFailed)?;
+        if rows.len() > 1 {
+            // e.g. a legacy numeric key restored next to its string key
+            warn!(
+                "Session {} is stored in {} rows, using the one expiring last. \
+                See find_duplicates."
+                , session_id
+                , rows.len()
+            );
+        }
+        let Some(data) = rows.into_iter().next() else {
+            return Ok(None)
+        };
+        let length = data.record.len();
+        match decode_record(&data.record) {
+            Ok(mut prelim_record) => {
+                prelim_record.id = *session_id;
+                apply_stored_expiry_date(&mut prelim_record, &data.expiry_date)?;
+                // rows written before versioning was enabled count as version 0
+                self.stamp_version(&mut prelim_record, data.version.unwrap_or(0));
                 Ok(Some(prelim_record))
             }
-            , None => Ok(None)
+            , Err(e) => {
+                self.skip_undecodable(session_id, length, e, true).await?;
+                Ok(None)
+            }
         }
     }
+
+    /// Deletes a session. What happens for a session that isn't in the
+    /// store is set with `with_missing_delete_behavior`.
+    /// ```
+    /// # use surrealdb::{Surreal, engine::local::Mem};
+    /// # use tower_sessions_surrealdb_store::{SchemaApplyMode, SurrealdbStore};
+    /// # use tower_sessions::{SessionStore, cookie::time::{Duration, OffsetDateTime}, session::{Id, Record}};
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// # let my_surreal = Surreal::new::<Mem>(()).await?;
+    /// # my_surreal.use_ns("namespace").use_db("database").await?;
+    /// # let my_surreal_store = SurrealdbStore::new(my_surreal, "sessions".into(), "sessions_latest_id".into());
+    /// # my_surreal_store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    /// # let mut record = Record {
+    /// #     id: Id::default()
+    /// #     , data: Default::default()
+    /// #     , expiry_date: OffsetDateTime::now_utc() + Duration::hours(1)
+    /// # };
+    /// # my_surreal_store.create(&mut record).await?;
+    /// my_surreal_store.delete(&record.id).await?;
+    /// assert_eq!(my_surreal_store.load(&record.id).await?, None);
+    /// # Ok(())
+    /// # }
+    /// ```
     async fn delete(&self, session_id: &Id) -> session_store::Result<()> {
-        let id_i64: i64 = session_id.0.try_into().map_err(|_| Encode(
-            "ID was out of range for target data type of i64".into()
-        ))?;
-        self.client
-            .delete::<Option<DatabaseRecord>>((&self.sessions_table, id_i64))
-            .await
-            .map_err(|e| Backend(e.to_string()))?;
+        let deleted = self.delete_returning(session_id).await?;
+        if !deleted && self.config.missing_delete_behavior == MissingDeleteBehavior::Error {
+            return Err(SurrealdbError::MissingSession(*session_id).into())
+        }
         Ok(())
     }
 }
\ No newline at end of file
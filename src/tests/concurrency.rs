@@ -0,0 +1,153 @@
+use super::*;
+use std::collections::HashSet;
+use surrealdb::engine::local::Db;
+
+/// Reads a positive number from the environment so that CI can dial the
+/// intensity of the harness, falling back to `default`.
+fn env_or(name: &str, default: u64) -> anyhow::Result<u64> {
+    match std::env::var(name) {
+        Ok(value) => value.parse()
+            .context(format!("{name} must be a number, was {value}"))
+        , Err(_) => Ok(default)
+    }
+}
+
+/// SplitMix64, enough to pick operations reproducibly from a seed.
+struct OperationPicker(u64);
+
+impl OperationPicker {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+/// Runs a random mix of operations against the shared store and checks
+/// that every session this task owns reads back as it was last written.
+async fn mixed_operations(
+    store: SurrealdbStore<Db>
+    , task: u64
+    , operations: u64
+    , seed: u64
+    , created_ids: Arc<Mutex<HashSet<Id>>>
+) -> anyhow::Result<()> {
+    let mut picker = OperationPicker(seed ^ task.wrapping_mul(0x2545_f491_4f6c_dd1d));
+    // kept in creation order, so that the same seed picks the same sessions
+    let mut owned: Vec<Record> = Vec::new();
+    for step in 0..operations {
+        let owned_index = match owned.len() {
+            0 => None
+            , len => Some(picker.below(len as u64) as usize)
+        };
+        match (picker.below(6), owned_index) {
+            (0 | 1, _) | (_, None) => {
+                let expired = picker.below(8) == 0;
+                let mut record = Record {
+                    id: Id(0)
+                    , data: HashMap::from([
+                        ("task".to_string(), json!(task))
+                        , ("step".to_string(), json!(step))
+                    ])
+                    , expiry_date: match expired {
+                        true => OffsetDateTime::now_utc().saturating_sub(Duration::minutes(1))
+                        , false => OffsetDateTime::now_utc().saturating_add(Duration::hours(1))
+                    }
+                };
+                store.create(&mut record).await
+                    .context(format!("Task {task} could not create a session at step {step}"))?;
+                let unique = created_ids.lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(record.id);
+                anyhow::ensure!(unique, "Task {task} was handed the taken ID {}", record.id);
+                if expired {
+                    anyhow::ensure!(
+                        store.load(&record.id).await?.is_none()
+                        , "Task {task} loaded the expired session {}"
+                        , record.id
+                    );
+                } else {
+                    owned.push(record);
+                }
+            }
+            , (2, Some(index)) => {
+                let record = &mut owned[index];
+                record.data.insert("step".to_string(), json!(step));
+                store.save(record).await
+                    .context(format!("Task {task} could not save session {} at step {step}", record.id))?;
+            }
+            , (3, Some(index)) => {
+                let id = owned.remove(index).id;
+                store.delete(&id).await
+                    .context(format!("Task {task} could not delete session {id} at step {step}"))?;
+                anyhow::ensure!(
+                    store.load(&id).await?.is_none()
+                    , "Task {task} still loaded session {id} after deleting it"
+                );
+            }
+            , (4, _) => {
+                store.delete_expired().await
+                    .context(format!("Task {task} could not delete expired sessions at step {step}"))?;
+            }
+            , (_, Some(index)) => {
+                let record = &owned[index];
+                let loaded = store.load(&record.id).await
+                    .context(format!("Task {task} could not load session {} at step {step}", record.id))?;
+                anyhow::ensure!(
+                    loaded.as_ref() == Some(record)
+                    , "Task {task} loaded {loaded:#?} for session {} but last wrote {record:#?}"
+                    , record.id
+                );
+            }
+        }
+    }
+    // nothing was lost or overwritten by the other tasks
+    for record in &owned {
+        let loaded = store.load(&record.id).await?;
+        anyhow::ensure!(
+            loaded.as_ref() == Some(record)
+            , "Task {task} ended with {loaded:#?} for session {} instead of {record:#?}"
+            , record.id
+        );
+    }
+    Ok(())
+}
+
+/// Many tasks hammer one store at once. Tune it with
+/// CONCURRENCY_TASKS, CONCURRENCY_OPERATIONS and CONCURRENCY_SEED.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_mixed_operations() -> anyhow::Result<()> {
+    let _ = *LOGGING_INIT;
+    let tasks = env_or("CONCURRENCY_TASKS", 32)?;
+    let operations = env_or("CONCURRENCY_OPERATIONS", 50)?;
+    let seed = env_or("CONCURRENCY_SEED", 0x5eed)?;
+    let store = SurrealdbStore::new_in_memory(
+        "namespace".into()
+        , "database".into()
+        , "sessions".into()
+        , "sessions_latest_id".into()
+    ).await?;
+    store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+    let created_ids = Arc::new(Mutex::new(HashSet::new()));
+    let handles: Vec<_> = (0..tasks)
+        .map(|task| tokio::spawn(mixed_operations(
+            store.clone()
+            , task
+            , operations
+            , seed
+            , created_ids.clone()
+        )))
+        .collect();
+    for (task, handle) in handles.into_iter().enumerate() {
+        handle.await
+            .context(format!("Task {task} panicked"))?
+            .context(format!("Task {task} failed with seed {seed}"))?;
+    }
+    Ok(())
+}
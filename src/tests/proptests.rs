@@ -0,0 +1,219 @@
+use super::*;
+use proptest::{
+    prelude::*
+    , collection::{hash_map, vec}
+    , test_runner::{TestCaseError, TestRunner}
+};
+use tokio::runtime::{Builder, Runtime};
+
+/// Arbitrary JSON values, including nested arrays and objects. Floats are
+/// left out since NaN never compares equal to itself.
+fn arb_json_value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null)
+        , any::<bool>().prop_map(Value::from)
+        , any::<i64>().prop_map(Value::from)
+        , ".{0,32}".prop_map(Value::from)
+    ];
+    leaf.prop_recursive(4, 32, 4, |inner| prop_oneof![
+        vec(inner.clone(), 0..4).prop_map(Value::Array)
+        , hash_map(".{0,16}", inner, 0..4)
+            .prop_map(|map| Value::Object(map.into_iter().collect()))
+    ])
+}
+
+fn arb_data() -> impl Strategy<Value = HashMap<String, Value>> {
+    hash_map(".{0,64}", arb_json_value(), 0..8)
+}
+
+/// Expiry is generated as an offset from the moment the case runs so that
+/// records are never already expired by the time they are created. The
+/// offset is at least a minute, which leaves a slow case time to load.
+fn arb_expiry_offset() -> impl Strategy<Value = Duration> {
+    prop_oneof![
+        Just(Duration::minutes(1))
+        , (60i64..=3_153_600_000).prop_map(Duration::seconds)
+    ]
+}
+
+/// Session IDs as tower-sessions generates them, uniform over i128 so about
+/// half are negative, plus the extremes of both i128 and i64.
+fn arb_session_id() -> impl Strategy<Value = i128> {
+    prop_oneof![
+        any::<i128>()
+        , prop::sample::select(vec![
+            i128::MIN
+            , i128::MIN + 1
+            , i64::MIN as i128 - 1
+            , i64::MIN as i128
+            , -1
+            , 0
+            , 1
+            , i64::MAX as i128
+            , i64::MAX as i128 + 1
+            , i128::MAX
+        ])
+    ]
+}
+
+fn runtime() -> anyhow::Result<Runtime> {
+    Ok(Builder::new_current_thread().enable_all().build()?)
+}
+
+/// Runs `cases` cases unless PROPTEST_CASES asks for another number, e.g.
+/// for a longer run in CI.
+fn config_with_cases(cases: u32) -> ProptestConfig {
+    match std::env::var("PROPTEST_CASES") {
+        // the default config reads PROPTEST_CASES
+        Ok(_) => ProptestConfig::default()
+        , Err(_) => ProptestConfig::with_cases(cases)
+    }
+}
+
+/// Runs create -> load -> save -> load -> delete -> load and checks that
+/// every step is consistent with the record that was written.
+async fn round_trip(
+    store: &SurrealdbStore<Any>
+    , data: HashMap<String, Value>
+    , expiry_offset: Duration
+) -> anyhow::Result<()> {
+    let mut record = Record {
+        id: Id(0)
+        , data: data
+        , expiry_date: OffsetDateTime::now_utc().saturating_add(expiry_offset)
+    };
+
+    store.create(&mut record).await
+        .context(format!("Could not create record. Record was: {:#?}", record))?;
+    let loaded_after_create = store.load(&record.id).await
+        .context(format!("Could not load record after create with id: {}", &record.id))?
+        .ok_or(anyhow!("Load after create was successfull but no data was returned"))?;
+    anyhow::ensure!(record == loaded_after_create
+        , "Loaded record after create differs. Expected {:#?}, got {:#?}"
+        , record
+        , loaded_after_create
+    );
+
+    record.data.insert("proptest_saved".into(), json!({"nested": [1, 2, 3]}));
+    store.save(&record).await
+        .context(format!("Could not save record. Record was: {:#?}", record))?;
+    let loaded_after_save = store.load(&record.id).await
+        .context(format!("Could not load record after save with id: {}", &record.id))?
+        .ok_or(anyhow!("Load after save was successfull but no data was returned"))?;
+    anyhow::ensure!(record == loaded_after_save
+        , "Loaded record after save differs. Expected {:#?}, got {:#?}"
+        , record
+        , loaded_after_save
+    );
+
+    store.delete(&record.id).await
+        .context(format!("Could not delete record with id: {}", &record.id))?;
+    let loaded_after_delete = store.load(&record.id).await
+        .context(format!("Could not load record after delete with id: {}", &record.id))?;
+    anyhow::ensure!(loaded_after_delete.is_none()
+        , "Record was still returned after delete: {:#?}"
+        , loaded_after_delete
+    );
+    Ok(())
+}
+
+#[test]
+fn arbitrary_record_round_trip() -> anyhow::Result<()> {
+    let _ = *LOGGING_INIT;
+    let runtime = runtime()?;
+    let store = runtime.block_on(async {
+        let store = create_store().await?;
+        store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+        anyhow::Ok(store)
+    })?;
+    let mut runner = TestRunner::new(config_with_cases(32));
+    runner.run(&(arb_data(), arb_expiry_offset()), |(data, expiry_offset)| {
+        runtime.block_on(round_trip(&store, data, expiry_offset))
+            .map_err(|e| TestCaseError::fail(format!("{e:#}")))
+    }).map_err(|e| anyhow!("Property based round trip failed: {e}"))?;
+    Ok(())
+}
+
+/// Runs save -> load -> delete -> load for a session whose ID was picked by
+/// the caller rather than by `create`.
+async fn saved_id_round_trip(store: &SurrealdbStore<Any>, id: i128) -> anyhow::Result<()> {
+    let record = Record {
+        id: Id(id)
+        , data: HashMap::from([("id".to_string(), json!(id.to_string()))])
+        , expiry_date: OffsetDateTime::now_utc().saturating_add(Duration::weeks(1))
+    };
+    store.save(&record).await
+        .context(format!("Could not save record with id {id}"))?;
+    let loaded = store.load(&record.id).await
+        .context(format!("Could not load record with id {id}"))?;
+    anyhow::ensure!(loaded.as_ref() == Some(&record)
+        , "Loaded record with id {} differs. Expected {:#?}, got {:#?}"
+        , id
+        , record
+        , loaded
+    );
+    store.delete(&record.id).await
+        .context(format!("Could not delete record with id {id}"))?;
+    let loaded_after_delete = store.load(&record.id).await
+        .context(format!("Could not load record after delete with id {id}"))?;
+    anyhow::ensure!(loaded_after_delete.is_none()
+        , "Record with id {} was still returned after delete"
+        , id
+    );
+    Ok(())
+}
+
+#[test]
+fn arbitrary_session_id_round_trip() -> anyhow::Result<()> {
+    let _ = *LOGGING_INIT;
+    let runtime = runtime()?;
+    let store = runtime.block_on(async {
+        let store = fresh_store("proptest_id").await?;
+        store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+        anyhow::Ok(store)
+    })?;
+    let mut runner = TestRunner::new(config_with_cases(64));
+    runner.run(&arb_session_id(), |id| {
+        runtime.block_on(saved_id_round_trip(&store, id))
+            .map_err(|e| TestCaseError::fail(format!("{e:#}")))
+    }).map_err(|e| anyhow!("Property based session ID round trip failed: {e}"))?;
+    Ok(())
+}
+
+#[test]
+fn edge_case_record_round_trip() -> anyhow::Result<()> {
+    let _ = *LOGGING_INIT;
+    let runtime = runtime()?;
+    runtime.block_on(async {
+        let store = create_store().await?;
+        store.create_data_model(SchemaApplyMode::IfNotExists).await?;
+
+        // empty data map
+        round_trip(&store, HashMap::new(), Duration::weeks(1)).await
+            .context("Round trip with empty data failed")?;
+
+        // nested JSON
+        let mut nested: HashMap<String, Value> = HashMap::new();
+        nested.insert(
+            "nested".into()
+            , json!({"a": {"b": [1, {"c": null}, "d"]}, "e": true})
+        );
+        round_trip(&store, nested, Duration::weeks(1)).await
+            .context("Round trip with nested data failed")?;
+
+        // very long string keys
+        let mut long_keys: HashMap<String, Value> = HashMap::new();
+        long_keys.insert("k".repeat(u16::MAX as usize), json!("long_key_value"));
+        round_trip(&store, long_keys, Duration::weeks(1)).await
+            .context("Round trip with very long keys failed")?;
+
+        // expiry in the distant future
+        round_trip(&store, HashMap::new(), Duration::weeks(52 * 1000)).await
+            .context("Round trip with distant expiry failed")?;
+
+        // expiry just 1 second in the future
+        round_trip(&store, HashMap::new(), Duration::seconds(1)).await
+            .context("Round trip with 1 second expiry failed")?;
+        anyhow::Ok(())
+    })
+}